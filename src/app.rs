@@ -1,22 +1,78 @@
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::hash::Hash;
-use std::path::PathBuf;
-use walkdir::WalkDir;
+use std::path::{Path, PathBuf};
 
+pub mod batch;
+pub mod duplicates;
+pub mod history;
+pub mod omdb;
+pub mod parse;
+pub mod plan;
+pub mod probe;
+pub mod profile;
+pub mod rename;
+pub mod sanitize;
+pub mod scan;
+pub mod secrets;
 mod settings;
+pub mod sorting;
+pub mod template;
+#[cfg(test)]
+mod ui_tests;
+pub mod watch;
+
+use batch::{BatchJob, JobStatus};
+use duplicates::{DuplicateGroup, KeepRule};
+use history::{History, HistoryEntry};
+use plan::{normalize_episode_number, target_file_name};
+use profile::SeriesProfile;
+use rename::{RenameOperation, RenameOutcome, RenameStatus};
+use sanitize::{NamingOptions, SanitizePolicy, UnicodeForm};
+use secrets::SecretStore;
+use settings::AppSettings;
+use sorting::{episode_number_cmp, natural_cmp, SortColumn, SortState};
+use template::TemplateValues;
+use watch::{ReviewQueue, WatchFolder};
 
 // Communication channel for sending data from background thread to UI thread
 #[derive(Debug)]
 enum AppMessage {
-    DataFetched(Vec<Episode>, Vec<LocalFile>),
+    DataFetched(FetchedSeason),
     FetchError(String),
+    // Progress shown while the fetch is still running, e.g. retries
+    FetchStatus(String),
+    // Progress of writing title tags in the background
+    TagStatus(String),
+    // Title tags of that many files were written, with one message per file
+    // that failed
+    TagsWritten(usize, Vec<String>),
+}
+
+// Result of fetching a season and scanning its directory
+#[derive(Debug)]
+struct FetchedSeason {
+    episodes: Vec<Episode>,
+    files: Vec<LocalFile>,
+    // Typical episode length from the provider
+    runtime_minutes: Option<u32>,
+    duplicates: Vec<DuplicateGroup>,
+}
+
+// Messages from the fetch threads of batch jobs, tagged with the job id
+#[derive(Debug)]
+enum BatchMessage {
+    Fetched(u64, FetchedSeason),
+    FetchError(u64, String),
+    FetchStatus(u64, String),
 }
 
 // Represents a local file found in the directory
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct LocalFile {
     pub path: PathBuf,
+    // Read from the container header, `None` for other files or if unreadable
+    pub media: Option<probe::MediaInfo>,
 }
 
 // --- Data Structures for OMDB API Response ---
@@ -30,11 +86,13 @@ pub struct Episode {
     pub imdb_id: String,
 }
 
-#[derive(Debug, Deserialize, Default)]
-#[serde(rename_all = "PascalCase")]
-struct SeasonResponse {
-    #[serde(default)]
-    pub episodes: Vec<Episode>,
+// Result of validating the episode number typed next to a file
+enum InputStatus<'a> {
+    Empty,
+    Valid(&'a Episode),
+    Duplicate(&'a Episode),
+    UnknownEpisode,
+    InvalidCharacters,
 }
 
 // Action to be taken after the confirmation dialog is closed
@@ -52,31 +110,164 @@ pub struct SeriesRenamer {
     pub series_directory: String,
     pub season_number: u32,
     pub show_process_window: bool,
+    /// Saved series, picked in the main panel.
+    pub profiles: Vec<SeriesProfile>,
+    /// Name of the profile the inputs were loaded from.
+    pub active_profile: Option<String>,
+    /// Directories and series processed recently, offered in the recent menus.
+    pub history: History,
 
+    #[serde(skip)]
+    settings: AppSettings,
+    // When the settings file was last read or written by us
+    #[serde(skip)]
+    settings_modified: Option<std::time::SystemTime>,
+    #[serde(skip)]
+    settings_checked_at: Option<std::time::Instant>,
     #[serde(skip)]
     api_key: String,
     #[serde(skip)]
+    secret_store: Option<Box<dyn SecretStore>>,
+    #[serde(skip)]
     episodes: Vec<Episode>,
     #[serde(skip)]
     files: Vec<LocalFile>,
+    // Typical episode length of the fetched season
+    #[serde(skip)]
+    episode_runtime: Option<u32>,
+    // Copies of the same episode among the files
+    #[serde(skip)]
+    duplicate_groups: Vec<DuplicateGroup>,
+    #[serde(skip)]
+    duplicate_status: String,
+    // Moves of extra copies waiting for confirmation
+    #[serde(skip)]
+    duplicate_moves: Vec<RenameOperation>,
+    // Files and duplicates before the last moves, restored when they are undone
+    #[serde(skip)]
+    duplicate_undo: Option<(Vec<LocalFile>, Vec<DuplicateGroup>)>,
     #[serde(skip)]
     fetch_status: String,
     #[serde(skip)]
     is_fetching: bool,
     #[serde(skip)]
     receiver: Option<crossbeam_channel::Receiver<AppMessage>>,
+    #[serde(skip)]
+    fetch_cancel: omdb::CancelToken,
 
     // The final plan to be confirmed
     #[serde(skip)]
-    rename_plan: HashMap<Episode, LocalFile>,
+    rename_plan: Vec<(Episode, LocalFile)>,
     // Holds the text from the input fields
     #[serde(skip)]
     file_episode_inputs: HashMap<PathBuf, String>,
+    // Options of the "Assign Sequentially" action
+    #[serde(skip)]
+    sequential_start_after: u32,
+    #[serde(skip)]
+    sequential_skip: String,
+    #[serde(skip)]
+    sequential_status: String,
+    // Inputs that were ignored while building the plan
+    #[serde(skip)]
+    plan_issues: Vec<String>,
+    #[serde(skip)]
+    show_validation_dialog: bool,
     #[serde(skip)]
     show_confirmation_dialog: bool,
     // Holds the action to be taken after the confirmation dialog
     #[serde(skip)]
     action_after_confirm: Option<DialogAction>,
+
+    // Per-file results of the last applied rename plan
+    #[serde(skip)]
+    rename_report: Vec<RenameOutcome>,
+    #[serde(skip)]
+    show_report_window: bool,
+    #[serde(skip)]
+    report_failures_only: bool,
+    #[serde(skip)]
+    report_status: String,
+    // Episode titles of the files in the report by their old path, written
+    // as title tags after renaming and after retries
+    #[serde(skip)]
+    report_titles: HashMap<PathBuf, String>,
+    // Title tags are written on threads that report through this channel
+    #[serde(skip)]
+    tag_channel: (
+        crossbeam_channel::Sender<AppMessage>,
+        crossbeam_channel::Receiver<AppMessage>,
+    ),
+    #[serde(skip)]
+    tag_writers: usize,
+
+    // State of the settings window
+    #[serde(skip)]
+    show_settings_window: bool,
+    // Settings being edited, applied only when valid
+    #[serde(skip)]
+    settings_draft: AppSettings,
+    #[serde(skip)]
+    api_key_input: String,
+    #[serde(skip)]
+    reveal_api_key: bool,
+    #[serde(skip)]
+    settings_status: String,
+    #[serde(skip)]
+    key_test_receiver: Option<crossbeam_channel::Receiver<Result<(), String>>>,
+
+    // State of the first-run wizard
+    #[serde(skip)]
+    show_onboarding: bool,
+    #[serde(skip)]
+    onboarding_key: String,
+    #[serde(skip)]
+    onboarding_status: String,
+    #[serde(skip)]
+    onboarding_receiver: Option<crossbeam_channel::Receiver<Result<(), String>>>,
+
+    // Sort order of the lists in the assignment and confirmation views
+    #[serde(skip)]
+    episodes_sort: SortState,
+    #[serde(skip)]
+    files_sort: SortState,
+    #[serde(skip)]
+    confirmation_sort: SortState,
+
+    // Files the watch daemon was unsure about
+    #[serde(skip)]
+    review_queue: ReviewQueue,
+    #[serde(skip)]
+    review_queue_modified: Option<std::time::SystemTime>,
+    #[serde(skip)]
+    show_review_window: bool,
+    #[serde(skip)]
+    review_status: String,
+
+    // Jobs of the batch queue, fetched concurrently and reviewed one by one
+    #[serde(skip)]
+    jobs: Vec<BatchJob>,
+    #[serde(skip)]
+    next_job_id: u64,
+    #[serde(skip)]
+    batch_channel: (
+        crossbeam_channel::Sender<BatchMessage>,
+        crossbeam_channel::Receiver<BatchMessage>,
+    ),
+    // Job whose files are shown in the assignment window
+    #[serde(skip)]
+    reviewing_job: Option<u64>,
+    #[serde(skip)]
+    show_batch_window: bool,
+
+    // State of the profile window
+    #[serde(skip)]
+    show_profile_window: bool,
+    #[serde(skip)]
+    profile_draft: SeriesProfile,
+    // Name of the profile being edited, `None` for a new one
+    #[serde(skip)]
+    profile_draft_original: Option<String>,
 }
 
 impl Default for SeriesRenamer {
@@ -86,16 +277,66 @@ impl Default for SeriesRenamer {
             series_directory: String::new(),
             season_number: 1,
             show_process_window: false,
+            profiles: Vec::new(),
+            active_profile: None,
+            history: History::default(),
+            settings: AppSettings::default(),
+            settings_modified: None,
+            settings_checked_at: None,
             api_key: String::new(),
+            secret_store: None,
             episodes: Vec::new(),
             files: Vec::new(),
+            episode_runtime: None,
+            duplicate_groups: Vec::new(),
+            duplicate_status: String::new(),
+            duplicate_moves: Vec::new(),
+            duplicate_undo: None,
             fetch_status: String::from("Waiting for user input..."),
             is_fetching: false,
             receiver: None,
-            rename_plan: HashMap::new(),
+            fetch_cancel: omdb::CancelToken::default(),
+            rename_plan: Vec::new(),
             file_episode_inputs: HashMap::new(),
+            sequential_start_after: 0,
+            sequential_skip: String::new(),
+            sequential_status: String::new(),
+            plan_issues: Vec::new(),
+            show_validation_dialog: false,
             show_confirmation_dialog: false,
             action_after_confirm: None,
+            rename_report: Vec::new(),
+            show_report_window: false,
+            report_failures_only: false,
+            report_status: String::new(),
+            report_titles: HashMap::new(),
+            tag_channel: crossbeam_channel::unbounded(),
+            tag_writers: 0,
+            show_settings_window: false,
+            settings_draft: AppSettings::default(),
+            api_key_input: String::new(),
+            reveal_api_key: false,
+            settings_status: String::new(),
+            key_test_receiver: None,
+            show_onboarding: false,
+            onboarding_key: String::new(),
+            onboarding_status: String::new(),
+            onboarding_receiver: None,
+            episodes_sort: SortState::new(SortColumn::Episode),
+            files_sort: SortState::new(SortColumn::FileName),
+            confirmation_sort: SortState::new(SortColumn::Episode),
+            review_queue: ReviewQueue::default(),
+            review_queue_modified: None,
+            show_review_window: false,
+            review_status: String::new(),
+            jobs: Vec::new(),
+            next_job_id: 0,
+            batch_channel: crossbeam_channel::unbounded(),
+            reviewing_job: None,
+            show_batch_window: false,
+            show_profile_window: false,
+            profile_draft: SeriesProfile::default(),
+            profile_draft_original: None,
         }
     }
 }
@@ -108,54 +349,129 @@ impl SeriesRenamer {
             Default::default()
         };
 
-        match confy::load("series_renamer", None) {
-            Ok(settings::AppSettings { api_key }) => {
-                app.api_key = api_key;
+        match AppSettings::load() {
+            Ok(mut settings) => {
+                app.load_api_key(&mut settings);
+                app.settings = settings;
             }
             Err(e) => {
                 app.fetch_status = format!("Error loading config: {}", e);
             }
         };
+        app.settings_modified = AppSettings::modified_time();
+        app.show_onboarding = !app.has_api_key();
 
         app
     }
+
+    /// Whether a real API key has been configured.
+    fn has_api_key(&self) -> bool {
+        let api_key = self.api_key.trim();
+        !api_key.is_empty() && api_key != settings::PLACEHOLDER_API_KEY
+    }
+
+    /// Reads the API key from the secret store, moving a plain text key from
+    /// the settings file of older versions into the store first.
+    fn load_api_key(&mut self, settings: &mut AppSettings) {
+        let store = match secrets::default_store() {
+            Ok(store) => store,
+            Err(e) => {
+                self.api_key = settings.api_key.clone();
+                self.fetch_status = format!("Could not open secret store: {}", e);
+                return;
+            }
+        };
+
+        if !settings.api_key.is_empty() {
+            let migrated = settings.api_key == settings::PLACEHOLDER_API_KEY
+                || store.store(&settings.api_key).is_ok();
+            if migrated {
+                settings.api_key.clear();
+                if let Err(e) = settings.store() {
+                    self.fetch_status = format!("Error saving config: {}", e);
+                }
+            } else {
+                self.api_key = settings.api_key.clone();
+            }
+        }
+
+        match store.load() {
+            Ok(Some(api_key)) => self.api_key = api_key,
+            Ok(None) => {}
+            Err(e) => self.fetch_status = format!("Could not read API key: {}", e),
+        }
+        self.api_key_input = self.api_key.clone();
+        self.secret_store = Some(store);
+    }
 }
 
 impl eframe::App for SeriesRenamer {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.reload_settings_if_changed(ctx);
+        self.ui(ctx);
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
+    }
+}
+
+impl SeriesRenamer {
+    /// Draws one frame. Unlike `update` this never reads the settings file,
+    /// so the UI tests can drive it without touching the user's config.
+    fn ui(&mut self, ctx: &egui::Context) {
         // --- Check for messages from background thread ---
         if self.is_fetching {
             if let Some(rx) = &self.receiver {
                 if let Ok(msg) = rx.try_recv() {
                     match msg {
-                        AppMessage::DataFetched(episodes, files) => {
-                            self.episodes = episodes;
-                            self.files = files;
+                        AppMessage::DataFetched(fetched) => {
+                            self.episodes = fetched.episodes;
+                            self.files = fetched.files;
+                            self.episode_runtime = fetched.runtime_minutes;
+                            self.duplicate_groups = fetched.duplicates;
+                            self.duplicate_status.clear();
+                            self.duplicate_undo = None;
                             self.rename_plan.clear();
                             self.file_episode_inputs.clear(); // Clear old inputs
+                            self.sequential_status.clear();
                             self.is_fetching = false;
                             self.fetch_status = format!(
                                 "Fetched {} episodes and {} files.",
                                 self.episodes.len(),
                                 self.files.len()
                             );
+                            self.record_history(self.fetch_status.clone());
                         }
                         AppMessage::FetchError(err_msg) => {
                             self.is_fetching = false;
+                            let summary = err_msg.lines().next().unwrap_or_default();
+                            self.record_history(format!("Fetch failed: {}", summary));
                             self.fetch_status = err_msg;
                         }
+                        AppMessage::FetchStatus(status) => {
+                            self.fetch_status = status;
+                        }
+                        // Only sent by title tag writers.
+                        AppMessage::TagStatus(_) | AppMessage::TagsWritten(..) => {}
                     }
                 }
             }
+            // Keep polling the channel while the background thread works.
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
         }
+        self.poll_batch_messages(ctx);
+        self.poll_tag_messages(ctx);
 
         // --- Main Window UI ---
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Series Renamer");
             ui.separator();
+            self.profile_picker_ui(ui);
             ui.horizontal(|ui| {
                 ui.label("IMDb Link:");
                 ui.text_edit_singleline(&mut self.imdb_link);
+                self.recent_menu_ui(ui, false);
             });
             ui.horizontal(|ui| {
                 ui.label("Season:");
@@ -169,97 +485,67 @@ impl eframe::App for SeriesRenamer {
                         self.series_directory = path.to_string_lossy().to_string();
                     }
                 }
+                self.recent_menu_ui(ui, true);
             });
-            if ui.add_enabled(!self.is_fetching, egui::Button::new("Process")).clicked() {
-                if !self.imdb_link.is_empty() && !self.series_directory.is_empty() {
-                    self.show_process_window = true;
-                    self.is_fetching = true;
-                    self.fetch_status = "Fetching data...".to_string();
-                    self.episodes.clear();
-                    self.files.clear();
-                    let (sender, receiver) = crossbeam_channel::unbounded();
-                    self.receiver = Some(receiver);
-                    let (api_key, imdb_link, series_dir, season_number) = (
-                        self.api_key.clone(),
-                        self.imdb_link.clone(),
-                        self.series_directory.clone(),
-                        self.season_number,
-                    );
-                    std::thread::spawn(move || {
-                        let files: Vec<LocalFile> = WalkDir::new(series_dir)
-                            .into_iter()
-                            .filter_map(Result::ok)
-                            .filter(|e| e.file_type().is_file())
-                            .map(|e| LocalFile {
-                                path: e.into_path(),
-                            })
-                            .collect();
-                        let imdb_id = match imdb_link.split('/').find(|s| s.starts_with("tt")) {
-                            Some(id) => id.to_string(),
-                            None => {
-                                let _ = sender.send(AppMessage::FetchError(
-                                    "Could not find IMDb ID in link.".to_string(),
-                                ));
-                                return;
-                            }
-                        };
-                        let request_url = format!(
-                            "http://www.omdbapi.com/?i={}&Season={}&apikey={}",
-                            imdb_id, season_number, api_key
-                        );
-                        let request = ehttp::Request::get(request_url);
-                        ehttp::fetch(request, move |result| match result {
-                            Ok(response) if response.ok => {
-                                match serde_json::from_slice::<SeasonResponse>(&response.bytes) {
-                                    Ok(season) => {
-                                        let _ = sender
-                                            .send(AppMessage::DataFetched(season.episodes, files));
-                                    }
-                                    Err(e) => {
-                                        let _ = sender.send(AppMessage::FetchError(format!(
-                                            "JSON Parse Error: {}",
-                                            e
-                                        )));
-                                    }
-                                }
-                            }
-                            Ok(response) => {
-                                let _ = sender.send(AppMessage::FetchError(format!(
-                                    "API Error: {} {}",
-                                    response.status, response.status_text
-                                )));
-                            }
-                            Err(e) => {
-                                let _ = sender
-                                    .send(AppMessage::FetchError(format!("Network Error: {}", e)));
-                            }
-                        });
-                    });
-                } else {
-                    self.fetch_status =
-                        "Please provide both an IMDb link and a directory.".to_string();
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!self.is_fetching, egui::Button::new("Process"))
+                    .clicked()
+                {
+                    self.start_fetch();
+                }
+                if ui
+                    .button("Add to Batch")
+                    .on_hover_text(
+                        "Fetch in the background and review later together with other series.",
+                    )
+                    .clicked()
+                {
+                    self.add_batch_job();
                 }
+                if let Some(profile) = self.active_profile() {
+                    let next_season = profile.season_number + 1;
+                    if ui
+                        .add_enabled(
+                            !self.is_fetching && next_season <= 99,
+                            egui::Button::new(format!("Process Season {}", next_season)),
+                        )
+                        .on_hover_text(
+                            "Load the profile and fetch the season after the one processed last.",
+                        )
+                        .clicked()
+                    {
+                        self.process_next_season();
+                    }
+                }
+            });
+            if self.is_fetching && ui.button("Cancel").clicked() {
+                self.cancel_fetch();
             }
             ui.label(&self.fetch_status);
+            if !self.rename_report.is_empty() && ui.button("Show Rename Report").clicked() {
+                self.show_report_window = true;
+            }
+            if !self.jobs.is_empty() && ui.button(format!("Batch ({})", self.jobs.len())).clicked()
+            {
+                self.show_batch_window = true;
+            }
+            if !self.review_queue.items.is_empty()
+                && ui
+                    .button(format!("Review Queue ({})", self.review_queue.items.len()))
+                    .clicked()
+            {
+                self.show_review_window = true;
+            }
 
             ui.separator();
 
-            if ui.button("Open Settings").clicked() {
-                match confy::get_configuration_file_path("series_renamer", None) {
-                    Ok(path) => {
-                        if let Err(e) = open::that(&path) {
-                            self.fetch_status = format!("Failed to open settings file: {}", e);
-                        }
-                    }
-                    Err(e) => {
-                        self.fetch_status = format!("Could not find settings file: {}", e);
-                    }
-                }
+            if ui.button("Settings").clicked() {
+                self.open_settings_window();
             }
 
             if ui.button("Get API Key").clicked() {
-                let url = "http://www.omdbapi.com/apikey.aspx";
-                if let Err(e) = open::that(url) {
+                if let Err(e) = open::that(omdb::API_KEY_URL) {
                     self.fetch_status = format!("Failed to open URL: {}", e);
                 }
             }
@@ -267,70 +553,38 @@ impl eframe::App for SeriesRenamer {
 
         // --- Processing and Confirmation Windows ---
         self.show_assignment_window(ctx);
+        self.show_validation_window(ctx);
         self.show_confirmation_window(ctx);
+        self.show_duplicate_moves_window(ctx);
+        self.show_report_window(ctx);
+        self.show_settings_window(ctx);
+        self.show_onboarding_window(ctx);
+        self.show_review_window(ctx);
+        self.show_profile_window(ctx);
+        self.show_batch_window(ctx);
 
         // --- Handle deferred actions ---
         if let Some(action) = self.action_after_confirm.take() {
             match action {
+                DialogAction::Confirm if self.reviewing_job.is_some() => {
+                    self.finish_review();
+                }
                 DialogAction::Confirm => {
-                    let mut rename_results = Vec::new();
-                    for (episode, file) in &self.rename_plan {
-                        let original_path = &file.path;
-                        if let Some(extension) = original_path.extension().and_then(|s| s.to_str())
-                        {
-                            if let Ok(episode_number) = episode.episode.parse::<u32>() {
-                                let sanitized_title = Self::sanitize_title(&episode.title);
-                                let new_name = format!(
-                                    "S{:02}E{:02} - {}.{}",
-                                    self.season_number, episode_number, sanitized_title, extension
-                                );
-
-                                if let Some(parent_dir) = original_path.parent() {
-                                    let new_path = parent_dir.join(&new_name);
-                                    match std::fs::rename(original_path, &new_path) {
-                                        Ok(_) => {
-                                            rename_results.push(format!(
-                                                "Successfully renamed '{}' to '{}'",
-                                                original_path.display(),
-                                                new_name
-                                            ));
-                                        }
-                                        Err(e) => {
-                                            rename_results.push(format!(
-                                                "ERROR renaming {}: {}",
-                                                original_path.display(),
-                                                e
-                                            ));
-                                        }
-                                    }
-                                } else {
-                                    rename_results.push(format!(
-                                        "ERROR: Could not get parent directory for {}",
-                                        original_path.display()
-                                    ));
-                                }
-                            } else {
-                                rename_results.push(format!(
-                                    "ERROR: Could not parse episode number '{}' for {}",
-                                    episode.episode,
-                                    original_path.display()
-                                ));
-                            }
-                        } else {
-                            rename_results.push(format!(
-                                "ERROR: Could not get file extension for {}",
-                                original_path.display()
-                            ));
-                        }
-                    }
-
-                    self.fetch_status = rename_results.join("\n");
+                    let (operations, failures) = self.plan_operations();
+                    let outcomes =
+                        rename::apply_plan(&operations, failures, self.settings.all_or_nothing);
+                    self.show_rename_report(outcomes);
+                    self.report_titles = plan_titles(&self.rename_plan);
+                    self.start_writing_title_tags(self.rename_report.clone());
+                    self.record_history(self.fetch_status.clone());
                     self.show_confirmation_dialog = false;
                     self.show_process_window = false;
                     self.rename_plan.clear();
                     self.episodes.clear();
                     self.files.clear();
+                    self.duplicate_groups.clear();
                     self.file_episode_inputs.clear();
+                    self.plan_issues.clear();
                 }
                 DialogAction::Cancel => {
                     self.show_confirmation_dialog = false;
@@ -338,135 +592,2078 @@ impl eframe::App for SeriesRenamer {
             }
         }
     }
-
-    fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
-    }
 }
 
 // --- Window and UI Logic ---
 impl SeriesRenamer {
-    fn sanitize_title(title: &str) -> String {
-        title.chars().filter(|c| c.is_alphanumeric() || c.is_whitespace()).collect()
+    /// Fetches the season and scans the directory in the background, then
+    /// opens the assignment window.
+    fn start_fetch(&mut self) {
+        if !self.has_api_key() {
+            self.fetch_status = "Please set up your OMDb API key first.".to_string();
+            self.show_onboarding = true;
+            return;
+        }
+        if self.imdb_link.is_empty() || self.series_directory.is_empty() {
+            self.fetch_status = "Please provide both an IMDb link and a directory.".to_string();
+            return;
+        }
+        let season_number = self.season_number;
+        if let Some(profile) = self.active_profile_mut() {
+            profile.season_number = season_number;
+        }
+
+        self.leave_review();
+        self.show_process_window = true;
+        self.is_fetching = true;
+        self.fetch_status = "Fetching data...".to_string();
+        self.episodes.clear();
+        self.files.clear();
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        self.receiver = Some(receiver);
+        self.fetch_cancel = omdb::CancelToken::default();
+        let (client, imdb_link, series_dir, duplicates_folder) = (
+            self.omdb_client(),
+            self.imdb_link.clone(),
+            self.series_directory.clone(),
+            self.settings.duplicates_folder.clone(),
+        );
+        let cancel = self.fetch_cancel.clone();
+        std::thread::spawn(move || {
+            let on_status = |status| {
+                let _ = sender.send(AppMessage::FetchStatus(status));
+            };
+            let message = match fetch_season_and_files(
+                &client,
+                &imdb_link,
+                series_dir,
+                &duplicates_folder,
+                season_number,
+                &cancel,
+                &on_status,
+            ) {
+                Ok(fetched) => AppMessage::DataFetched(fetched),
+                Err(e) => AppMessage::FetchError(e),
+            };
+            let _ = sender.send(message);
+        });
     }
 
-    fn show_assignment_window(&mut self, ctx: &egui::Context) {
-        if !self.show_process_window {
+    /// Adds the current inputs to the batch queue and starts fetching them.
+    fn add_batch_job(&mut self) {
+        if !self.has_api_key() {
+            self.fetch_status = "Please set up your OMDb API key first.".to_string();
+            self.show_onboarding = true;
+            return;
+        }
+        if self.imdb_link.is_empty() || self.series_directory.is_empty() {
+            self.fetch_status = "Please provide both an IMDb link and a directory.".to_string();
             return;
         }
+        let id = self.next_job_id;
+        self.next_job_id += 1;
+        self.jobs.push(BatchJob::new(
+            id,
+            self.imdb_link.clone(),
+            self.season_number,
+            self.series_directory.clone(),
+            self.naming().clone(),
+        ));
+        self.start_job_fetch(id);
+        self.fetch_status = format!("Added season {} to the batch.", self.season_number);
+    }
 
-        let mut is_open = self.show_process_window;
-        egui::Window::new("Assign Files to Episodes")
-            .id(egui::Id::new("assignment_window"))
-            .open(&mut is_open)
-            .vscroll(false)
-            .resizable(true)
-            .default_size([900.0, 600.0])
-            .show(ctx, |ui| {
-                if self.is_fetching {
-                    ui.centered_and_justified(|ui| {
-                        ui.spinner();
-                        ui.label("Fetching data...");
-                    });
-                } else if self.episodes.is_empty() {
-                    ui.centered_and_justified(|ui| {
-                        ui.label(&self.fetch_status);
-                    });
-                } else {
-                    self.assignment_ui(ui);
+    /// Fetches a job of the batch queue on its own thread, so the jobs are
+    /// fetched concurrently.
+    fn start_job_fetch(&mut self, id: u64) {
+        let client = self.omdb_client();
+        let sender = self.batch_channel.0.clone();
+        let duplicates_folder = self.settings.duplicates_folder.clone();
+        let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) else {
+            return;
+        };
+        job.status = JobStatus::Fetching(String::new());
+        job.cancel = omdb::CancelToken::default();
+        let (imdb_link, series_dir, season_number, cancel) = (
+            job.imdb_link.clone(),
+            job.series_directory.clone(),
+            job.season_number,
+            job.cancel.clone(),
+        );
+        std::thread::spawn(move || {
+            let on_status = |status| {
+                let _ = sender.send(BatchMessage::FetchStatus(id, status));
+            };
+            let message = match fetch_season_and_files(
+                &client,
+                &imdb_link,
+                series_dir,
+                &duplicates_folder,
+                season_number,
+                &cancel,
+                &on_status,
+            ) {
+                Ok(fetched) => BatchMessage::Fetched(id, fetched),
+                Err(e) => BatchMessage::FetchError(id, e),
+            };
+            let _ = sender.send(message);
+        });
+    }
+
+    fn poll_batch_messages(&mut self, ctx: &egui::Context) {
+        while let Ok(message) = self.batch_channel.1.try_recv() {
+            let id = match &message {
+                BatchMessage::Fetched(id, ..)
+                | BatchMessage::FetchError(id, _)
+                | BatchMessage::FetchStatus(id, _) => *id,
+            };
+            // Removed jobs may still send their result.
+            let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) else {
+                continue;
+            };
+            match message {
+                BatchMessage::Fetched(_, fetched) => {
+                    job.episodes = fetched.episodes;
+                    job.files = fetched.files;
+                    job.runtime_minutes = fetched.runtime_minutes;
+                    job.duplicates = fetched.duplicates;
+                    job.status = JobStatus::Ready;
                 }
+                BatchMessage::FetchError(_, error) => job.status = JobStatus::Failed(error),
+                BatchMessage::FetchStatus(_, status) => job.status = JobStatus::Fetching(status),
+            }
+        }
+        if self
+            .jobs
+            .iter()
+            .any(|job| matches!(job.status, JobStatus::Fetching(_)))
+        {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+    }
 
-                ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
-                    ui.add_space(8.0);
-                    if !self.files.is_empty() && ui.button("Confirm Rename Plan").clicked() {
-                        self.build_rename_plan();
-                        if !self.rename_plan.is_empty() {
-                            self.show_confirmation_dialog = true;
-                        }
-                    }
-                    ui.separator();
-                });
+    fn reviewing_job(&self) -> Option<&BatchJob> {
+        let id = self.reviewing_job?;
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    /// Shows the files of a batch job in the assignment window.
+    fn review_job(&mut self, id: u64) {
+        self.leave_review();
+        let Some(position) = self.jobs.iter().position(|job| job.id == id) else {
+            return;
+        };
+        let job = &self.jobs[position];
+        self.imdb_link = job.imdb_link.clone();
+        self.season_number = job.season_number;
+        self.series_directory = job.series_directory.clone();
+        self.episodes = job.episodes.clone();
+        self.files = job.files.clone();
+        self.episode_runtime = job.runtime_minutes;
+        self.duplicate_groups = job.duplicates.clone();
+        self.duplicate_status.clear();
+        self.file_episode_inputs = job.inputs.clone();
+        self.sequential_status.clear();
+        self.rename_plan.clear();
+        self.plan_issues.clear();
+        self.fetch_status = format!(
+            "Reviewing job {} of {} in the batch.",
+            position + 1,
+            self.jobs.len()
+        );
+        self.reviewing_job = Some(id);
+        self.show_process_window = true;
+    }
+
+    /// Reviews the first job that was fetched but not reviewed yet. Returns
+    /// `false` if there is none.
+    fn review_next_job(&mut self) -> bool {
+        match self.jobs.iter().find(|job| job.status == JobStatus::Ready) {
+            Some(job) => {
+                self.review_job(job.id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops reviewing a batch job, keeping the typed episode numbers for
+    /// the next review.
+    fn leave_review(&mut self) {
+        let Some(id) = self.reviewing_job.take() else {
+            return;
+        };
+        let inputs = std::mem::take(&mut self.file_episode_inputs);
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+            job.inputs = inputs;
+            // Extra copies may have been moved away during the review.
+            job.files = self.files.clone();
+            job.duplicates = std::mem::take(&mut self.duplicate_groups);
+        }
+        self.episodes.clear();
+        self.files.clear();
+        self.rename_plan.clear();
+        self.plan_issues.clear();
+    }
+
+    /// Saves the confirmed plan to the job under review and moves on to the
+    /// next one.
+    fn finish_review(&mut self) {
+        let plan = std::mem::take(&mut self.rename_plan);
+        if let Some(job) = self
+            .jobs
+            .iter_mut()
+            .find(|job| Some(job.id) == self.reviewing_job)
+        {
+            job.plan = plan;
+            job.status = JobStatus::Reviewed;
+        }
+        self.leave_review();
+        self.show_confirmation_dialog = false;
+        if !self.review_next_job() {
+            self.show_process_window = false;
+            self.show_batch_window = true;
+            self.fetch_status = "All jobs of the batch are reviewed.".to_string();
+        }
+    }
+
+    fn remove_job(&mut self, id: u64) {
+        if self.reviewing_job == Some(id) {
+            self.leave_review();
+            self.show_process_window = false;
+        }
+        if let Some(job) = self.jobs.iter().find(|job| job.id == id) {
+            job.cancel.cancel();
+        }
+        self.jobs.retain(|job| job.id != id);
+    }
+
+    /// Renames the files of every reviewed job and shows one report for all
+    /// of them. Jobs that are not reviewed stay in the queue.
+    fn apply_batch(&mut self) {
+        self.leave_review();
+        self.show_process_window = false;
+        let (reviewed, rest): (Vec<BatchJob>, Vec<BatchJob>) = std::mem::take(&mut self.jobs)
+            .into_iter()
+            .partition(|job| job.status == JobStatus::Reviewed);
+        self.jobs = rest;
+
+        let mut outcomes = Vec::new();
+        let mut titles = HashMap::new();
+        for job in &reviewed {
+            let job_outcomes = job.apply(self.settings.all_or_nothing);
+            titles.extend(plan_titles(&job.plan));
+            self.history.record(HistoryEntry {
+                series_directory: job.series_directory.clone(),
+                imdb_link: job.imdb_link.clone(),
+                season_number: job.season_number,
+                timestamp: history::now_timestamp(),
+                outcome: outcome_summary(&job_outcomes),
             });
-        self.show_process_window = is_open;
+            outcomes.extend(job_outcomes);
+        }
+        self.show_rename_report(outcomes);
+        self.report_titles = titles;
+        self.start_writing_title_tags(self.rename_report.clone());
+        self.fetch_status = format!(
+            "Applied {} batch job(s). {}",
+            reviewed.len(),
+            self.fetch_status
+        );
     }
 
-    fn show_confirmation_window(&mut self, ctx: &egui::Context) {
-        if !self.show_confirmation_dialog {
+    fn show_batch_window(&mut self, ctx: &egui::Context) {
+        if !self.show_batch_window {
             return;
         }
 
-        egui::Window::new("Confirm Renames")
-            .collapsible(false)
-            .resizable(false)
-            .open(&mut self.show_confirmation_dialog)
+        let mut is_open = self.show_batch_window;
+        let mut review = None;
+        let mut retry = None;
+        let mut remove = None;
+        let mut review_next_clicked = false;
+        let mut apply_clicked = false;
+        egui::Window::new("Batch")
+            .id(egui::Id::new("batch_window"))
+            .open(&mut is_open)
+            .resizable(true)
             .show(ctx, |ui| {
-                ui.label("Are you sure you want to perform the following renames?");
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    for (episode, file) in &self.rename_plan {
-                        let extension = file.path.extension().and_then(|s| s.to_str()).unwrap_or("");
-                        let sanitized_title = Self::sanitize_title(&episode.title);
-                        let new_name = if let Ok(episode_number) = episode.episode.parse::<u32>() {
-                            format!("S{:02}E{:02} - {}.{}", self.season_number, episode_number, sanitized_title, extension)
-                        } else {
-                            format!("S{:02}E{} - {}.{}", self.season_number, episode.episode, sanitized_title, extension)
-                        };
-                        ui.label(format!(
-                            "{} -> {}",
-                            file.path.file_name().unwrap().to_str().unwrap(),
-                            new_name
-                        ));
-                    }
-                });
+                if self.jobs.is_empty() {
+                    ui.label(
+                        "The batch is empty. \
+                         Use \"Add to Batch\" in the main window to add series.",
+                    );
+                    return;
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("batch_grid")
+                            .num_columns(5)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong("IMDb Link");
+                                ui.strong("Season");
+                                ui.strong("Directory");
+                                ui.strong("Status");
+                                ui.label("");
+                                ui.end_row();
+
+                                for job in &self.jobs {
+                                    ui.label(&job.imdb_link);
+                                    ui.label(job.season_number.to_string());
+                                    ui.label(&job.series_directory);
+                                    match &job.status {
+                                        JobStatus::Failed(_) => {
+                                            ui.colored_label(
+                                                ui.visuals().error_fg_color,
+                                                job.status.label(),
+                                            );
+                                        }
+                                        status => {
+                                            ui.label(status.label());
+                                        }
+                                    }
+                                    ui.horizontal(|ui| {
+                                        let reviewable = matches!(
+                                            job.status,
+                                            JobStatus::Ready | JobStatus::Reviewed
+                                        );
+                                        if ui
+                                            .add_enabled(
+                                                reviewable && !self.is_fetching,
+                                                egui::Button::new("Review"),
+                                            )
+                                            .clicked()
+                                        {
+                                            review = Some(job.id);
+                                        }
+                                        if matches!(job.status, JobStatus::Failed(_))
+                                            && ui.button("Retry").clicked()
+                                        {
+                                            retry = Some(job.id);
+                                        }
+                                        if ui.button("Remove").clicked() {
+                                            remove = Some(job.id);
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
                 ui.separator();
+                let count = |status: fn(&JobStatus) -> bool| {
+                    self.jobs.iter().filter(|job| status(&job.status)).count()
+                };
+                let ready = count(|status| *status == JobStatus::Ready);
+                let reviewed = count(|status| *status == JobStatus::Reviewed);
+                let fetching = count(|status| matches!(status, JobStatus::Fetching(_)));
+                ui.label(format!(
+                    "{} of {} job(s) reviewed, {} ready for review, {} fetching.",
+                    reviewed,
+                    self.jobs.len(),
+                    ready,
+                    fetching
+                ));
                 ui.horizontal(|ui| {
-                    if ui.button("Confirm").clicked() {
-                        self.action_after_confirm = Some(DialogAction::Confirm);
+                    if ui
+                        .add_enabled(
+                            ready > 0 && !self.is_fetching,
+                            egui::Button::new("Review Next"),
+                        )
+                        .clicked()
+                    {
+                        review_next_clicked = true;
                     }
-                    if ui.button("Cancel").clicked() {
-                        self.action_after_confirm = Some(DialogAction::Cancel);
+                    if ui
+                        .add_enabled(reviewed > 0, egui::Button::new("Apply Batch"))
+                        .on_hover_text("Rename the files of every reviewed job.")
+                        .clicked()
+                    {
+                        apply_clicked = true;
                     }
                 });
             });
+
+        if let Some(id) = review {
+            self.review_job(id);
+        }
+        if let Some(id) = retry {
+            self.start_job_fetch(id);
+        }
+        if let Some(id) = remove {
+            self.remove_job(id);
+        }
+        if review_next_clicked {
+            self.review_next_job();
+        }
+        if apply_clicked {
+            self.apply_batch();
+        }
+        self.show_batch_window = is_open;
     }
 
-    /// Builds the rename plan from the user's text inputs.
-    fn build_rename_plan(&mut self) {
-        self.rename_plan.clear();
+    fn active_profile(&self) -> Option<&SeriesProfile> {
+        let name = self.active_profile.as_ref()?;
+        self.profiles.iter().find(|profile| &profile.name == name)
+    }
 
-        // Create a quick lookup map from episode number string to the Episode struct.
-        let episode_map: HashMap<String, Episode> = self
-            .episodes
-            .iter()
-            .map(|e| (e.episode.clone(), e.clone()))
-            .collect();
+    fn active_profile_mut(&mut self) -> Option<&mut SeriesProfile> {
+        let name = self.active_profile.as_ref()?;
+        self.profiles
+            .iter_mut()
+            .find(|profile| &profile.name == name)
+    }
 
-        for file in &self.files {
-            // Get the user's input for the current file.
-            if let Some(episode_num_str) = self.file_episode_inputs.get(&file.path) {
-                // If the input is not empty, find the corresponding episode.
-                if !episode_num_str.is_empty() {
-                    if let Some(episode) = episode_map.get(episode_num_str) {
-                        // We found a match, add it to the plan.
-                        self.rename_plan.insert(episode.clone(), file.clone());
-                    }
-                }
-            }
+    /// Naming options of the job being reviewed or the active profile, or
+    /// the ones from the settings.
+    fn naming(&self) -> &NamingOptions {
+        if let Some(job) = self.reviewing_job() {
+            return &job.naming;
         }
+        self.active_profile()
+            .map_or(&self.settings.naming, |profile| &profile.naming)
     }
 
-    /// This function contains the primary UI logic for manual assignment.
+    /// A client for the active profile's server, or the one from the settings.
+    fn omdb_client(&self) -> omdb::Client {
+        let mut client = self.settings.omdb_client(&self.api_key);
+        if let Some(profile) = self.active_profile() {
+            if !profile.base_url.trim().is_empty() {
+                client.base_url = profile.base_url.trim().to_string();
+            }
+        }
+        client
+    }
+
+    /// Makes a profile active and fills the inputs from it. `None` keeps the
+    /// inputs and goes back to the naming options from the settings.
+    fn select_profile(&mut self, name: Option<String>) {
+        self.active_profile = name;
+        if let Some(profile) = self.active_profile() {
+            let profile = profile.clone();
+            self.imdb_link = profile.imdb_link;
+            self.series_directory = profile.series_directory;
+            self.season_number = profile.season_number;
+        }
+    }
+
+    /// Loads the active profile with the season after the last processed
+    /// one and starts fetching.
+    fn process_next_season(&mut self) {
+        let Some(name) = self.active_profile.clone() else {
+            return;
+        };
+        self.select_profile(Some(name));
+        self.season_number = (self.season_number + 1).min(99);
+        self.start_fetch();
+    }
+
+    /// Opens the profile window, either for the active profile or for a new
+    /// one prefilled from the current inputs.
+    fn open_profile_window(&mut self, edit_active: bool) {
+        match self.active_profile().filter(|_| edit_active).cloned() {
+            Some(profile) => {
+                self.profile_draft_original = Some(profile.name.clone());
+                self.profile_draft = profile;
+            }
+            None => {
+                self.profile_draft = SeriesProfile {
+                    name: String::new(),
+                    imdb_link: self.imdb_link.clone(),
+                    series_directory: self.series_directory.clone(),
+                    season_number: self.season_number,
+                    base_url: String::new(),
+                    naming: self.naming().clone(),
+                };
+                self.profile_draft_original = None;
+            }
+        }
+        self.show_profile_window = true;
+    }
+
+    /// The profile picker of the main panel.
+    fn profile_picker_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Profile:");
+            let mut selected = self.active_profile.clone();
+            egui::ComboBox::from_id_salt("profile_picker")
+                .selected_text(selected.as_deref().unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, None, "None");
+                    for profile in &self.profiles {
+                        ui.selectable_value(
+                            &mut selected,
+                            Some(profile.name.clone()),
+                            &profile.name,
+                        );
+                    }
+                });
+            if selected != self.active_profile {
+                self.select_profile(selected);
+            }
+            if ui.button("Save as Profile...").clicked() {
+                self.open_profile_window(false);
+            }
+            if self.active_profile().is_some() {
+                if ui.button("Edit").clicked() {
+                    self.open_profile_window(true);
+                }
+                if ui.button("Delete").clicked() {
+                    let name = self.active_profile.take();
+                    self.profiles
+                        .retain(|profile| Some(&profile.name) != name.as_ref());
+                }
+            }
+        });
+    }
+
+    fn show_profile_window(&mut self, ctx: &egui::Context) {
+        if !self.show_profile_window {
+            return;
+        }
+
+        let others: Vec<SeriesProfile> = self
+            .profiles
+            .iter()
+            .filter(|profile| Some(&profile.name) != self.profile_draft_original.as_ref())
+            .cloned()
+            .collect();
+        let problems = self.profile_draft.validate(&others);
+        let mut is_open = self.show_profile_window;
+        let mut save_clicked = false;
+        let mut cancel_clicked = false;
+        egui::Window::new("Series Profile")
+            .id(egui::Id::new("profile_window"))
+            .open(&mut is_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let draft = &mut self.profile_draft;
+                egui::Grid::new("profile_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut draft.name);
+                        ui.end_row();
+
+                        ui.label("IMDb Link:");
+                        ui.text_edit_singleline(&mut draft.imdb_link);
+                        ui.end_row();
+
+                        ui.label("Series Directory:");
+                        folder_input(ui, &mut draft.series_directory, "Directory");
+                        ui.end_row();
+
+                        ui.label("Last Season:");
+                        ui.add(egui::DragValue::new(&mut draft.season_number).range(1..=99));
+                        ui.end_row();
+
+                        ui.label("Base URL:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut draft.base_url)
+                                .hint_text("From settings")
+                                .desired_width(250.0),
+                        );
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                naming_ui(ui, &mut draft.naming, "profile");
+
+                ui.separator();
+                for problem in &problems {
+                    ui.colored_label(ui.visuals().error_fg_color, problem);
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(problems.is_empty(), egui::Button::new("Save"))
+                        .clicked()
+                    {
+                        save_clicked = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel_clicked = true;
+                    }
+                });
+            });
+
+        if save_clicked {
+            let mut profile = self.profile_draft.clone();
+            profile.name = profile.name.trim().to_string();
+            let name = profile.name.clone();
+            match self
+                .profiles
+                .iter()
+                .position(|p| Some(&p.name) == self.profile_draft_original.as_ref())
+            {
+                Some(index) => self.profiles[index] = profile,
+                None => self.profiles.push(profile),
+            }
+            self.profiles.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+            self.select_profile(Some(name));
+            is_open = false;
+        }
+        if cancel_clicked {
+            is_open = false;
+        }
+        self.show_profile_window = is_open;
+    }
+
+    /// Turns the rename plan into file operations. Files for which no target
+    /// path can be computed are returned as failed outcomes instead.
+    fn plan_operations(&self) -> (Vec<RenameOperation>, Vec<RenameOutcome>) {
+        plan::plan_operations(&self.rename_plan, self.season_number, self.naming())
+    }
+
+    /// Builds the rename plan from the user's text inputs.
+    /// Every input that could not be used is recorded in `plan_issues`.
+    fn build_rename_plan(&mut self) {
+        let plan = plan::build_rename_plan(
+            &self.episodes,
+            &self.files,
+            &self.file_episode_inputs,
+            self.season_number,
+            self.naming(),
+        );
+        self.rename_plan = plan.entries;
+        self.plan_issues = plan.issues;
+    }
+
+    /// Validates the text typed next to a file and resolves it to an episode.
+    fn input_status<'a>(
+        episodes: &'a [Episode],
+        input: &str,
+        input_counts: &HashMap<String, usize>,
+    ) -> InputStatus<'a> {
+        if input.trim().is_empty() {
+            return InputStatus::Empty;
+        }
+        let Some(number) = normalize_episode_number(input) else {
+            return InputStatus::InvalidCharacters;
+        };
+        let episode = episodes
+            .iter()
+            .find(|e| normalize_episode_number(&e.episode).as_deref() == Some(number.as_str()));
+        match episode {
+            Some(episode) if input_counts.get(&number).copied().unwrap_or(0) > 1 => {
+                InputStatus::Duplicate(episode)
+            }
+            Some(episode) => InputStatus::Valid(episode),
+            None => InputStatus::UnknownEpisode,
+        }
+    }
+
+    /// Counts how often each normalized episode number has been typed.
+    fn input_counts(&self) -> HashMap<String, usize> {
+        let mut input_counts: HashMap<String, usize> = HashMap::new();
+        for input in self.file_episode_inputs.values() {
+            if let Some(number) = normalize_episode_number(input) {
+                *input_counts.entry(number).or_default() += 1;
+            }
+        }
+        input_counts
+    }
+
+    fn show_assignment_window(&mut self, ctx: &egui::Context) {
+        if !self.show_process_window {
+            return;
+        }
+
+        let mut is_open = self.show_process_window;
+        let mut cancel_clicked = false;
+        egui::Window::new("Assign Files to Episodes")
+            .id(egui::Id::new("assignment_window"))
+            .open(&mut is_open)
+            .vscroll(false)
+            .resizable(true)
+            .default_size([900.0, 600.0])
+            .show(ctx, |ui| {
+                if self.is_fetching {
+                    ui.vertical_centered(|ui| {
+                        ui.spinner();
+                        ui.label(&self.fetch_status);
+                        if ui.button("Cancel").clicked() {
+                            cancel_clicked = true;
+                        }
+                    });
+                } else if self.episodes.is_empty() {
+                    ui.centered_and_justified(|ui| {
+                        ui.label(&self.fetch_status);
+                    });
+                } else {
+                    self.assignment_ui(ui);
+                }
+
+                ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
+                    ui.add_space(8.0);
+                    if !self.files.is_empty() && ui.button("Confirm Rename Plan").clicked() {
+                        self.build_rename_plan();
+                        if !self.plan_issues.is_empty() {
+                            self.show_validation_dialog = true;
+                        } else if !self.rename_plan.is_empty() {
+                            self.show_confirmation_dialog = true;
+                        }
+                    }
+                    ui.separator();
+                });
+            });
+        if cancel_clicked {
+            self.cancel_fetch();
+        }
+        if !is_open {
+            self.leave_review();
+        }
+        self.show_process_window = is_open;
+    }
+
+    /// Aborts the running fetch and makes the Process button usable again.
+    fn cancel_fetch(&mut self) {
+        self.fetch_cancel.cancel();
+        self.receiver = None;
+        self.is_fetching = false;
+        self.fetch_status = "Fetch cancelled.".to_string();
+    }
+
+    fn show_confirmation_window(&mut self, ctx: &egui::Context) {
+        if !self.show_confirmation_dialog {
+            return;
+        }
+
+        // Files that swap names with each other cannot be renamed one by one.
+        let (operations, _) = self.plan_operations();
+        let cycles = rename::find_cycles(&operations);
+        let mut save_settings = false;
+        let naming = self.naming().clone();
+        let is_batch_review = self.reviewing_job.is_some();
+
+        egui::Window::new("Confirm Renames")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut self.show_confirmation_dialog)
+            .show(ctx, |ui| {
+                ui.label(if is_batch_review {
+                    "Add the following renames to the batch? \
+                     They are applied together with the other jobs."
+                } else {
+                    "Are you sure you want to perform the following renames?"
+                });
+
+                let season_number = self.season_number;
+                let mut rows: Vec<(&Episode, String, String)> = self
+                    .rename_plan
+                    .iter()
+                    .map(|(episode, file)| {
+                        let old_name = file.path.file_name().unwrap().to_string_lossy().to_string();
+                        let new_name = target_file_name(season_number, &naming, episode, file)
+                            .unwrap_or_else(|e| e);
+                        (episode, old_name, new_name)
+                    })
+                    .collect();
+                let sort = self.confirmation_sort;
+                rows.sort_by(|a, b| {
+                    sort.apply(match sort.column {
+                        SortColumn::FileName => natural_cmp(&a.1, &b.1),
+                        SortColumn::NewName => natural_cmp(&a.2, &b.2),
+                        SortColumn::Episode | SortColumn::Title => {
+                            episode_number_cmp(&a.0.episode, &b.0.episode)
+                        }
+                    })
+                });
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("confirmation_grid")
+                        .num_columns(3)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            self.confirmation_sort
+                                .header(ui, "Episode", SortColumn::Episode);
+                            self.confirmation_sort
+                                .header(ui, "Current Name", SortColumn::FileName);
+                            self.confirmation_sort
+                                .header(ui, "New Name", SortColumn::NewName);
+                            ui.end_row();
+
+                            for (episode, old_name, new_name) in &rows {
+                                ui.label(format!("E{}", episode.episode));
+                                ui.label(old_name);
+                                ui.label(new_name);
+                                ui.end_row();
+                            }
+                        });
+                });
+
+                if !cycles.is_empty() {
+                    ui.separator();
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "{} rename cycle(s) detected. \
+                             They will be resolved through temporary names:",
+                            cycles.len()
+                        ),
+                    );
+                    for cycle in &cycles {
+                        let mut names: Vec<String> = cycle
+                            .iter()
+                            .map(|&i| {
+                                operations[i]
+                                    .from
+                                    .file_name()
+                                    .unwrap_or_default()
+                                    .to_string_lossy()
+                                    .to_string()
+                            })
+                            .collect();
+                        names.push(names[0].clone());
+                        ui.label(names.join(" -> "));
+                    }
+                }
+
+                ui.separator();
+                if ui
+                    .checkbox(&mut self.settings.all_or_nothing, "All or nothing")
+                    .on_hover_text(ALL_OR_NOTHING_HINT)
+                    .changed()
+                {
+                    save_settings = true;
+                }
+                if ui
+                    .checkbox(&mut self.settings.write_title_tags, "Write title tags")
+                    .on_hover_text(TITLE_TAGS_HINT)
+                    .changed()
+                {
+                    save_settings = true;
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(if is_batch_review {
+                            "Save to Batch"
+                        } else {
+                            "Confirm"
+                        })
+                        .clicked()
+                    {
+                        self.action_after_confirm = Some(DialogAction::Confirm);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.action_after_confirm = Some(DialogAction::Cancel);
+                    }
+                });
+            });
+        if save_settings {
+            self.store_settings();
+        }
+    }
+
+    /// Stores the outcomes of a rename run and opens the report window.
+    fn show_rename_report(&mut self, outcomes: Vec<RenameOutcome>) {
+        self.duplicate_undo = None;
+        self.report_titles.clear();
+        self.fetch_status = outcome_summary(&outcomes);
+        self.rename_report = outcomes;
+        self.report_status.clear();
+        self.show_report_window = true;
+    }
+
+    /// Writes the title tags of the renamed files among `outcomes` on a
+    /// separate thread if enabled, as rewriting the headers of large files
+    /// takes a while. Progress and problems are shown in the report window.
+    fn start_writing_title_tags(&mut self, outcomes: Vec<RenameOutcome>) {
+        if !self.settings.write_title_tags {
+            return;
+        }
+        let tags = title_tags(&self.report_titles, &outcomes);
+        if tags.is_empty() {
+            return;
+        }
+        self.tag_writers += 1;
+        self.report_status = format!("Writing the title tags of {} file(s)...", tags.len());
+        let sender = self.tag_channel.0.clone();
+        std::thread::spawn(move || {
+            let on_status = |status| {
+                let _ = sender.send(AppMessage::TagStatus(status));
+            };
+            let problems = write_title_tags(&tags, &on_status);
+            let _ = sender.send(AppMessage::TagsWritten(tags.len(), problems));
+        });
+    }
+
+    /// Shows the progress of the title tag writers in the report window.
+    fn poll_tag_messages(&mut self, ctx: &egui::Context) {
+        while let Ok(message) = self.tag_channel.1.try_recv() {
+            match message {
+                AppMessage::TagStatus(status) => self.report_status = status,
+                AppMessage::TagsWritten(count, problems) => {
+                    self.tag_writers = self.tag_writers.saturating_sub(1);
+                    self.report_status = match problems.first() {
+                        Some(first) => format!(
+                            "Could not write the title tag of {} file(s). {}",
+                            problems.len(),
+                            first
+                        ),
+                        None => format!("Wrote the title tags of {} file(s).", count),
+                    };
+                }
+                // Only sent by fetches.
+                AppMessage::DataFetched(_)
+                | AppMessage::FetchError(_)
+                | AppMessage::FetchStatus(_) => {}
+            }
+        }
+        if self.tag_writers > 0 {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+    }
+
+    /// Remembers the current directory and series with the outcome of
+    /// processing them.
+    fn record_history(&mut self, outcome: String) {
+        self.history.record(HistoryEntry {
+            series_directory: self.series_directory.clone(),
+            imdb_link: self.imdb_link.clone(),
+            season_number: self.season_number,
+            timestamp: history::now_timestamp(),
+            outcome,
+        });
+    }
+
+    /// A menu of recently processed combinations. With `directories` every
+    /// combination is listed and picking one fills in all inputs, otherwise
+    /// only the latest entry per series is listed and the directory is kept.
+    fn recent_menu_ui(&mut self, ui: &mut egui::Ui, directories: bool) {
+        if self.history.entries.is_empty() {
+            return;
+        }
+        let now = history::now_timestamp();
+        let entries = if directories {
+            self.history.entries.iter().collect()
+        } else {
+            self.history.series()
+        };
+        let mut picked = None;
+        ui.menu_button("Recent", |ui| {
+            for entry in entries {
+                let name = if directories {
+                    entry.series_directory.clone()
+                } else {
+                    let folder = Path::new(&entry.series_directory)
+                        .file_name()
+                        .map(|name| format!(" ({})", name.to_string_lossy()))
+                        .unwrap_or_default();
+                    format!("{}{}", entry.imdb_link, folder)
+                };
+                let details = format!(
+                    "Season {}, {}: {}",
+                    entry.season_number,
+                    history::age_label(entry.timestamp, now),
+                    entry.outcome
+                );
+                if ui.button(format!("{}\n{}", name, details)).clicked() {
+                    picked = Some(entry.clone());
+                    ui.close_menu();
+                }
+            }
+        });
+        let Some(entry) = picked else {
+            return;
+        };
+        if directories {
+            self.series_directory = entry.series_directory;
+        }
+        self.imdb_link = entry.imdb_link;
+        self.season_number = entry.season_number;
+        // Naming options follow the profile saved for this series, if any.
+        self.active_profile = self
+            .profiles
+            .iter()
+            .find(|p| p.imdb_link == self.imdb_link && p.series_directory == self.series_directory)
+            .map(|p| p.name.clone());
+    }
+
+    /// Runs every failed rename that has a target path again.
+    fn retry_failed_renames(&mut self) {
+        let mut outcomes = std::mem::take(&mut self.rename_report);
+        let retry: Vec<(usize, RenameOperation)> = outcomes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, outcome)| Some((index, outcome.retry_operation()?)))
+            .collect();
+        let operations: Vec<RenameOperation> = retry.iter().map(|(_, op)| op.clone()).collect();
+        let results = if self.settings.all_or_nothing {
+            rename::apply_renames_transactional(&operations)
+        } else {
+            rename::apply_renames(&operations)
+        };
+        for ((index, _), result) in retry.into_iter().zip(results.iter()) {
+            outcomes[index] = result.clone();
+        }
+        let titles = std::mem::take(&mut self.report_titles);
+        self.show_rename_report(outcomes);
+        self.report_titles = titles;
+        self.start_writing_title_tags(results);
+    }
+
+    /// Asks for a destination and writes the report as CSV or JSON.
+    fn export_rename_report(&mut self, extension: &str) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name(format!("rename_report.{}", extension))
+            .add_filter(extension.to_uppercase(), &[extension])
+            .save_file()
+        else {
+            return;
+        };
+        let contents = if extension == "json" {
+            rename::outcomes_to_json(&self.rename_report).map_err(|e| e.to_string())
+        } else {
+            Ok(rename::outcomes_to_csv(&self.rename_report))
+        };
+        self.report_status =
+            match contents.and_then(|c| std::fs::write(&path, c).map_err(|e| e.to_string())) {
+                Ok(()) => format!("Report saved to {}", path.display()),
+                Err(e) => format!("Failed to save report: {}", e),
+            };
+    }
+
+    fn show_report_window(&mut self, ctx: &egui::Context) {
+        if !self.show_report_window {
+            return;
+        }
+
+        let mut is_open = self.show_report_window;
+        let mut retry_clicked = false;
+        let mut undo_clicked = false;
+        let mut export_as = None;
+        egui::Window::new("Rename Report")
+            .id(egui::Id::new("rename_report_window"))
+            .open(&mut is_open)
+            .resizable(true)
+            .default_size([800.0, 400.0])
+            .show(ctx, |ui| {
+                let failed = self
+                    .rename_report
+                    .iter()
+                    .filter(|o| o.status == RenameStatus::Failed)
+                    .count();
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} renamed, {} failed",
+                        self.rename_report.len() - failed,
+                        failed
+                    ));
+                    ui.checkbox(&mut self.report_failures_only, "Show failures only");
+                    if ui
+                        .add_enabled(failed > 0, egui::Button::new("Retry Failed"))
+                        .clicked()
+                    {
+                        retry_clicked = true;
+                    }
+                    if self.duplicate_undo.is_some()
+                        && ui
+                            .button("Undo Moves")
+                            .on_hover_text("Move the extra copies back to where they were.")
+                            .clicked()
+                    {
+                        undo_clicked = true;
+                    }
+                    if ui.button("Export CSV").clicked() {
+                        export_as = Some("csv");
+                    }
+                    if ui.button("Export JSON").clicked() {
+                        export_as = Some("json");
+                    }
+                });
+                if !self.report_status.is_empty() {
+                    ui.label(&self.report_status);
+                }
+                ui.separator();
+
+                egui::ScrollArea::both().show(ui, |ui| {
+                    egui::Grid::new("rename_report_grid")
+                        .num_columns(4)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("Status");
+                            ui.strong("Old Name");
+                            ui.strong("New Name");
+                            ui.strong("Error");
+                            ui.end_row();
+
+                            for outcome in &self.rename_report {
+                                if self.report_failures_only
+                                    && outcome.status != RenameStatus::Failed
+                                {
+                                    continue;
+                                }
+                                match outcome.status {
+                                    RenameStatus::Renamed => ui.colored_label(
+                                        egui::Color32::from_rgb(90, 170, 90),
+                                        "Renamed",
+                                    ),
+                                    RenameStatus::Failed => {
+                                        ui.colored_label(ui.visuals().error_fg_color, "Failed")
+                                    }
+                                };
+                                ui.label(
+                                    outcome
+                                        .old_path
+                                        .file_name()
+                                        .unwrap_or_default()
+                                        .to_string_lossy(),
+                                )
+                                .on_hover_text(outcome.old_path.to_string_lossy());
+                                match &outcome.new_path {
+                                    Some(new_path) => ui.label(
+                                        new_path.file_name().unwrap_or_default().to_string_lossy(),
+                                    ),
+                                    None => ui.label("-"),
+                                };
+                                ui.label(outcome.error.as_deref().unwrap_or(""));
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+
+        if retry_clicked {
+            self.retry_failed_renames();
+        }
+        if undo_clicked {
+            self.undo_duplicate_moves();
+        }
+        if let Some(extension) = export_as {
+            self.export_rename_report(extension);
+        }
+        self.show_report_window = is_open;
+    }
+
+    /// Opens the settings window with the current settings as the draft.
+    fn open_settings_window(&mut self) {
+        self.settings_draft = self.settings.clone();
+        self.api_key_input = self.api_key.clone();
+        self.settings_status.clear();
+        self.show_settings_window = true;
+    }
+
+    /// Writes the current settings to the settings file.
+    fn store_settings(&mut self) {
+        match self.settings.store() {
+            Ok(()) => self.settings_modified = AppSettings::modified_time(),
+            Err(e) => self.settings_status = format!("Error saving config: {}", e),
+        }
+    }
+
+    /// Reloads the settings and the review queue if their files were changed
+    /// outside of the app.
+    fn reload_settings_if_changed(&mut self, ctx: &egui::Context) {
+        const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+        let now = std::time::Instant::now();
+        if self
+            .settings_checked_at
+            .is_some_and(|checked| now.duration_since(checked) < CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.settings_checked_at = Some(now);
+        ctx.request_repaint_after(CHECK_INTERVAL);
+        self.reload_review_queue();
+
+        let modified = AppSettings::modified_time();
+        if modified.is_none() || modified == self.settings_modified {
+            return;
+        }
+        self.settings_modified = modified;
+        match AppSettings::load() {
+            Ok(settings) if !settings.validate().is_empty() => {
+                self.fetch_status = format!(
+                    "Settings file was not reloaded: {}",
+                    settings.validate().join(" ")
+                );
+            }
+            Ok(mut settings) => {
+                if !settings.api_key.is_empty() {
+                    self.load_api_key(&mut settings);
+                }
+                // Keep unsaved edits in the settings window if nothing changed underneath.
+                if self.settings_draft == self.settings {
+                    self.settings_draft = settings.clone();
+                }
+                self.settings = settings;
+                self.fetch_status = "Settings reloaded from disk.".to_string();
+            }
+            Err(e) => {
+                self.fetch_status = format!("Error loading config: {}", e);
+            }
+        }
+    }
+
+    /// Checks the settings window inputs. Returns one message per problem.
+    fn validate_settings_inputs(&self) -> Vec<String> {
+        let mut problems = self.settings_draft.validate();
+        let api_key = self.api_key_input.trim();
+        if !api_key.chars().all(|c| c.is_ascii_alphanumeric()) {
+            problems.push("The API key may only contain letters and digits.".to_string());
+        }
+        if api_key == settings::PLACEHOLDER_API_KEY {
+            problems.push("Replace the placeholder with your own API key.".to_string());
+        }
+        problems
+    }
+
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        if !self.show_settings_window {
+            return;
+        }
+
+        // --- Check for the result of a key test ---
+        if let Some(rx) = &self.key_test_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.settings_status = match result {
+                    Ok(()) => "The API key is valid.".to_string(),
+                    Err(e) => format!("Key test failed: {}", e),
+                };
+                self.key_test_receiver = None;
+            } else {
+                ctx.request_repaint_after(std::time::Duration::from_millis(100));
+            }
+        }
+
+        let problems = self.validate_settings_inputs();
+        let mut is_open = self.show_settings_window;
+        let mut apply_clicked = false;
+        let mut revert_clicked = false;
+        let mut test_clicked = false;
+        let mut open_file_clicked = false;
+        egui::Window::new("Settings")
+            .id(egui::Id::new("settings_window"))
+            .open(&mut is_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.heading("OMDb API Key");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.api_key_input)
+                            .password(!self.reveal_api_key)
+                            .hint_text("API key")
+                            .desired_width(200.0),
+                    );
+                    ui.checkbox(&mut self.reveal_api_key, "Show");
+                    let is_testing = self.key_test_receiver.is_some();
+                    if ui
+                        .add_enabled(
+                            !is_testing && !self.api_key_input.trim().is_empty(),
+                            egui::Button::new("Test Key"),
+                        )
+                        .clicked()
+                    {
+                        test_clicked = true;
+                    }
+                    if is_testing {
+                        ui.spinner();
+                    }
+                });
+                if let Some(store) = &self.secret_store {
+                    ui.label(format!("Stored in the {}.", store.description()));
+                }
+
+                ui.separator();
+                ui.heading("Renaming");
+                ui.checkbox(&mut self.settings_draft.all_or_nothing, "All or nothing")
+                    .on_hover_text(ALL_OR_NOTHING_HINT);
+                ui.checkbox(
+                    &mut self.settings_draft.write_title_tags,
+                    "Write title tags",
+                )
+                .on_hover_text(TITLE_TAGS_HINT);
+                ui.horizontal(|ui| {
+                    let label = ui.label("Duplicates folder:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.settings_draft.duplicates_folder)
+                            .hint_text(duplicates::DEFAULT_FOLDER)
+                            .desired_width(200.0),
+                    )
+                    .on_hover_text(DUPLICATES_FOLDER_HINT)
+                    .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Keep:");
+                    egui::ComboBox::from_id_salt("settings_keep_rule")
+                        .selected_text(self.settings_draft.keep_rule.label())
+                        .show_ui(ui, |ui| {
+                            for rule in KeepRule::ALL {
+                                ui.selectable_value(
+                                    &mut self.settings_draft.keep_rule,
+                                    rule,
+                                    rule.label(),
+                                );
+                            }
+                        });
+                });
+                naming_ui(ui, &mut self.settings_draft.naming, "settings");
+
+                ui.separator();
+                ui.heading("Network");
+                egui::Grid::new("network_settings_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Base URL:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.settings_draft.base_url)
+                                .hint_text(omdb::DEFAULT_BASE_URL)
+                                .desired_width(250.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Request timeout:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.settings_draft.request_timeout_secs)
+                                .range(1..=300)
+                                .suffix(" s"),
+                        );
+                        ui.end_row();
+
+                        ui.label("Retries:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.settings_draft.max_retries)
+                                .range(0..=10),
+                        );
+                        ui.end_row();
+
+                        ui.label("First retry after:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.settings_draft.retry_backoff_ms)
+                                .range(100..=60_000)
+                                .speed(50)
+                                .suffix(" ms"),
+                        );
+                        ui.end_row();
+                    });
+
+                if self.settings_draft.base_url.trim().starts_with("http://") {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "Plain HTTP sends your API key unencrypted.",
+                    );
+                }
+
+                ui.separator();
+                ui.heading("Watch Folders");
+                ui.label(
+                    "New episodes in these folders are renamed \
+                     by running series_renamer --watch.",
+                );
+                let mut removed = None;
+                egui::Grid::new("watch_folders_grid")
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        ui.label("Directory");
+                        ui.label("IMDb Link");
+                        ui.label("Move To");
+                        ui.end_row();
+                        for (index, folder) in
+                            self.settings_draft.watch_folders.iter_mut().enumerate()
+                        {
+                            ui.push_id(index, |ui| {
+                                folder_input(ui, &mut folder.directory, "Folder to watch");
+                            });
+                            ui.add(
+                                egui::TextEdit::singleline(&mut folder.imdb_link)
+                                    .hint_text("Detect from file name")
+                                    .desired_width(200.0),
+                            );
+                            ui.push_id(("target", index), |ui| {
+                                folder_input(ui, &mut folder.target_directory, "Rename in place");
+                            });
+                            if ui.button("Remove").clicked() {
+                                removed = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if let Some(index) = removed {
+                    self.settings_draft.watch_folders.remove(index);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Add Folder...").clicked() {
+                        if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                            self.settings_draft.watch_folders.push(WatchFolder {
+                                directory: path.to_string_lossy().to_string(),
+                                ..Default::default()
+                            });
+                        }
+                    }
+                    ui.label("Handle files unchanged for:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.settings_draft.watch_settle_secs)
+                            .range(1..=3600)
+                            .suffix(" s"),
+                    );
+                });
+
+                ui.separator();
+                for problem in &problems {
+                    ui.colored_label(ui.visuals().error_fg_color, problem);
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(problems.is_empty(), egui::Button::new("Apply"))
+                        .clicked()
+                    {
+                        apply_clicked = true;
+                    }
+                    if ui.button("Revert").clicked() {
+                        revert_clicked = true;
+                    }
+                    if ui.button("Open Settings File").clicked() {
+                        open_file_clicked = true;
+                    }
+                });
+                if !self.settings_status.is_empty() {
+                    ui.label(&self.settings_status);
+                }
+            });
+
+        if apply_clicked {
+            self.settings_status.clear();
+            self.settings = self.settings_draft.clone();
+            self.store_settings();
+            if self.api_key_input.trim() != self.api_key {
+                self.save_api_key();
+            } else if self.settings_status.is_empty() {
+                self.settings_status = "Settings applied.".to_string();
+            }
+        }
+        if revert_clicked {
+            self.open_settings_window();
+        }
+        if open_file_clicked {
+            match AppSettings::file_path() {
+                Ok(path) => {
+                    if let Err(e) = open::that(&path) {
+                        self.settings_status = format!("Failed to open settings file: {}", e);
+                    }
+                }
+                Err(e) => {
+                    self.settings_status = format!("Could not find settings file: {}", e);
+                }
+            }
+        }
+        if test_clicked {
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            self.key_test_receiver = Some(receiver);
+            self.settings_status = "Testing key...".to_string();
+            let client = self.settings_draft.omdb_client(self.api_key_input.trim());
+            std::thread::spawn(move || {
+                let result = client.test_api_key();
+                let _ = sender.send(result.map_err(|e| e.with_guidance()));
+            });
+        }
+        self.show_settings_window = is_open;
+    }
+
+    /// Guides a new user through getting, verifying and saving an API key.
+    fn show_onboarding_window(&mut self, ctx: &egui::Context) {
+        if !self.show_onboarding {
+            return;
+        }
+
+        // --- Check for the result of the verification request ---
+        if let Some(rx) = &self.onboarding_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.onboarding_receiver = None;
+                match result {
+                    Ok(()) => {
+                        self.api_key_input = self.onboarding_key.trim().to_string();
+                        self.save_api_key();
+                        if self.has_api_key() {
+                            self.fetch_status = "API key verified and saved.".to_string();
+                            self.show_onboarding = false;
+                            return;
+                        }
+                        self.onboarding_status = self.settings_status.clone();
+                    }
+                    Err(e) => {
+                        self.onboarding_status = format!("The key was not accepted: {}", e);
+                    }
+                }
+            } else {
+                ctx.request_repaint_after(std::time::Duration::from_millis(100));
+            }
+        }
+
+        let mut is_open = self.show_onboarding;
+        let mut verify_clicked = false;
+        egui::Window::new("Welcome to Series Renamer")
+            .id(egui::Id::new("onboarding_window"))
+            .open(&mut is_open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(
+                    "Episode titles are looked up on OMDb, which needs a free personal API key.",
+                );
+                ui.add_space(4.0);
+                ui.label("1. Request a key on the OMDb website. It is sent to you by email.");
+                if ui.button("Open OMDb API Key Page").clicked() {
+                    if let Err(e) = open::that(omdb::API_KEY_URL) {
+                        self.onboarding_status = format!("Failed to open URL: {}", e);
+                    }
+                }
+                ui.add_space(4.0);
+                ui.label("2. Activate the key using the link in the email.");
+                ui.add_space(4.0);
+                ui.label("3. Paste the key here:");
+                let is_verifying = self.onboarding_receiver.is_some();
+                ui.horizontal(|ui| {
+                    let response = ui.add_enabled(
+                        !is_verifying,
+                        egui::TextEdit::singleline(&mut self.onboarding_key)
+                            .hint_text("API key")
+                            .desired_width(200.0),
+                    );
+                    let submitted =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    let key = self.onboarding_key.trim();
+                    let is_plausible = !key.is_empty()
+                        && key != settings::PLACEHOLDER_API_KEY
+                        && key.chars().all(|c| c.is_ascii_alphanumeric());
+                    if (ui
+                        .add_enabled(
+                            !is_verifying && is_plausible,
+                            egui::Button::new("Verify and Save"),
+                        )
+                        .clicked()
+                        || submitted)
+                        && is_plausible
+                    {
+                        verify_clicked = true;
+                    }
+                    if is_verifying {
+                        ui.spinner();
+                    }
+                });
+                if !self.onboarding_status.is_empty() {
+                    ui.colored_label(ui.visuals().warn_fg_color, &self.onboarding_status);
+                }
+                ui.separator();
+                ui.label("You can change the key later in Settings.");
+            });
+
+        if verify_clicked {
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            self.onboarding_receiver = Some(receiver);
+            self.onboarding_status = "Verifying key...".to_string();
+            let client = self.settings.omdb_client(self.onboarding_key.trim());
+            std::thread::spawn(move || {
+                let result = client.test_api_key();
+                let _ = sender.send(result.map_err(|e| e.with_guidance()));
+            });
+        }
+        self.show_onboarding = is_open;
+    }
+
+    /// Writes the key from the settings window to the secret store.
+    fn save_api_key(&mut self) {
+        let api_key = self.api_key_input.trim().to_string();
+        let result = match &self.secret_store {
+            Some(store) if api_key.is_empty() => store.delete(),
+            Some(store) => store.store(&api_key),
+            None => Err("No secret store is available".to_string()),
+        };
+        self.settings_status = match result {
+            Ok(()) => {
+                self.api_key = api_key;
+                "API key saved.".to_string()
+            }
+            Err(e) => format!("Failed to save API key: {}", e),
+        };
+    }
+
+    /// Reloads the review queue if the daemon changed it.
+    fn reload_review_queue(&mut self) {
+        let Ok(path) = ReviewQueue::file_path() else {
+            return;
+        };
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if modified == self.review_queue_modified {
+            return;
+        }
+        self.review_queue_modified = modified;
+        match ReviewQueue::load_from(&path) {
+            Ok(queue) => self.review_queue = queue,
+            Err(e) => self.review_status = format!("Could not read the review queue: {}", e),
+        }
+    }
+
+    /// Removes a file from the review queue on disk.
+    fn remove_from_review_queue(&mut self, path: &std::path::Path) {
+        let result = ReviewQueue::file_path()
+            .and_then(|queue_path| ReviewQueue::update(&queue_path, |queue| queue.remove(path)));
+        match result {
+            Ok(queue) => self.review_queue = queue,
+            Err(e) => self.review_status = format!("Could not write the review queue: {}", e),
+        }
+    }
+
+    /// Lists the files the watch daemon did not rename on its own.
+    fn show_review_window(&mut self, ctx: &egui::Context) {
+        if !self.show_review_window {
+            return;
+        }
+
+        let mut is_open = self.show_review_window;
+        let mut accepted = None;
+        let mut assigned = None;
+        let mut dismissed = None;
+        egui::Window::new("Review Queue")
+            .open(&mut is_open)
+            .resizable(true)
+            .default_size([700.0, 400.0])
+            .show(ctx, |ui| {
+                if self.review_queue.items.is_empty() {
+                    ui.label("No files are waiting for review.");
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        for (index, item) in self.review_queue.items.iter().enumerate() {
+                            ui.label(item.path.file_name().unwrap_or_default().to_string_lossy())
+                                .on_hover_text(item.path.to_string_lossy());
+                            ui.colored_label(ui.visuals().warn_fg_color, &item.reason);
+                            if let Some(suggestion) = &item.suggestion {
+                                ui.label(format!("-> {}", suggestion.display()));
+                            }
+                            ui.horizontal(|ui| {
+                                if item.suggestion.is_some() && ui.button("Accept").clicked() {
+                                    accepted = Some(index);
+                                }
+                                if ui
+                                    .button("Assign Manually")
+                                    .on_hover_text("Load the folder into the main window.")
+                                    .clicked()
+                                {
+                                    assigned = Some(index);
+                                }
+                                if ui.button("Dismiss").clicked() {
+                                    dismissed = Some(index);
+                                }
+                            });
+                            ui.separator();
+                        }
+                    });
+                if !self.review_status.is_empty() {
+                    ui.label(&self.review_status);
+                }
+            });
+
+        if let Some(item) = accepted.and_then(|index| self.review_queue.items.get(index).cloned()) {
+            let operation = RenameOperation {
+                from: item.path.clone(),
+                to: item.suggestion.unwrap_or_default(),
+            };
+            match watch::move_file(&operation) {
+                Ok(()) => {
+                    self.review_status = format!(
+                        "Renamed '{}' to '{}'.",
+                        operation.from.display(),
+                        operation.to.display()
+                    );
+                    self.remove_from_review_queue(&item.path);
+                }
+                Err(e) => self.review_status = format!("Renaming failed: {}", e),
+            }
+        }
+        if let Some(item) = assigned.and_then(|index| self.review_queue.items.get(index).cloned()) {
+            if let Some(imdb_id) = &item.imdb_id {
+                self.imdb_link = format!("https://www.imdb.com/title/{}/", imdb_id);
+            }
+            if let Some(season) = item.season {
+                self.season_number = season;
+            }
+            if let Some(parent) = item.path.parent() {
+                self.series_directory = parent.to_string_lossy().to_string();
+            }
+            self.fetch_status = "Check the IMDb link and season, then press Process.".to_string();
+            is_open = false;
+        }
+        if let Some(item) = dismissed.and_then(|index| self.review_queue.items.get(index).cloned())
+        {
+            self.remove_from_review_queue(&item.path);
+        }
+        self.show_review_window = is_open;
+    }
+
+    fn show_validation_window(&mut self, ctx: &egui::Context) {
+        if !self.show_validation_dialog {
+            return;
+        }
+
+        let mut is_open = self.show_validation_dialog;
+        let mut continue_clicked = false;
+        let mut back_clicked = false;
+        egui::Window::new("Check Assignments")
+            .collapsible(false)
+            .resizable(true)
+            .open(&mut is_open)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} input(s) could not be used and will be ignored:",
+                    self.plan_issues.len()
+                ));
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for issue in &self.plan_issues {
+                            ui.colored_label(ui.visuals().warn_fg_color, issue);
+                        }
+                    });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!self.rename_plan.is_empty(), egui::Button::new("Continue"))
+                        .on_disabled_hover_text("No valid assignments to rename.")
+                        .clicked()
+                    {
+                        continue_clicked = true;
+                    }
+                    if ui.button("Back").clicked() {
+                        back_clicked = true;
+                    }
+                });
+            });
+
+        if continue_clicked {
+            self.show_confirmation_dialog = true;
+        }
+        self.show_validation_dialog = is_open && !continue_clicked && !back_clicked;
+    }
+
+    /// Options and button of the "Assign Sequentially" action.
+    fn sequential_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .button("Assign Sequentially")
+                .on_hover_text(
+                    "Assign the video files in name order to the episodes in number order.",
+                )
+                .clicked()
+            {
+                self.assign_sequentially();
+            }
+            let label = ui.label("Start after episode:");
+            ui.add(egui::DragValue::new(&mut self.sequential_start_after).range(0..=999))
+                .labelled_by(label.id);
+            let label = ui.label("Skip:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.sequential_skip)
+                    .hint_text("e.g. 3, 7-9")
+                    .desired_width(80.0),
+            )
+            .on_hover_text("Episodes without a file, they are left out.")
+            .labelled_by(label.id);
+        });
+        if !self.sequential_status.is_empty() {
+            ui.label(&self.sequential_status);
+        }
+    }
+
+    /// Lists copies of the same episode with the one the keep rule picks,
+    /// and moves the others away.
+    fn duplicates_ui(&mut self, ui: &mut egui::Ui) {
+        if self.duplicate_groups.is_empty() {
+            if !self.duplicate_status.is_empty() {
+                ui.label(&self.duplicate_status);
+            }
+            return;
+        }
+        egui::CollapsingHeader::new(format!("Duplicates ({})", self.duplicate_groups.len()))
+            .default_open(true)
+            .show(ui, |ui| {
+                let mut save_settings = false;
+                ui.horizontal(|ui| {
+                    ui.label("Keep:");
+                    egui::ComboBox::from_id_salt("keep_rule")
+                        .selected_text(self.settings.keep_rule.label())
+                        .show_ui(ui, |ui| {
+                            for rule in KeepRule::ALL {
+                                save_settings |= ui
+                                    .selectable_value(
+                                        &mut self.settings.keep_rule,
+                                        rule,
+                                        rule.label(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                    if ui
+                        .button("Move Extra Copies")
+                        .on_hover_text(format!(
+                            "Move the copies that are not kept to '{}'.",
+                            self.duplicates_folder().display()
+                        ))
+                        .clicked()
+                    {
+                        self.plan_duplicate_moves();
+                    }
+                });
+                for group in &self.duplicate_groups {
+                    ui.label(group.label());
+                    let best = group.best(self.settings.keep_rule);
+                    for (index, copy) in group.files.iter().enumerate() {
+                        let name = copy
+                            .file
+                            .path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy();
+                        let size = format!("{:.1} MB", copy.size as f64 / 1_000_000.0);
+                        if index == best {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(90, 170, 90),
+                                format!("  Keep: {} ({})", name, size),
+                            );
+                        } else {
+                            ui.weak(format!("  Move: {} ({})", name, size));
+                        }
+                    }
+                }
+                if !self.duplicate_status.is_empty() {
+                    ui.label(&self.duplicate_status);
+                }
+                if save_settings {
+                    self.store_settings();
+                }
+            });
+    }
+
+    fn duplicates_folder(&self) -> PathBuf {
+        duplicates::resolve_folder(
+            Path::new(&self.series_directory),
+            &self.settings.duplicates_folder,
+        )
+    }
+
+    /// Plans moving every copy the keep rule does not pick to the duplicates
+    /// folder and asks for confirmation.
+    fn plan_duplicate_moves(&mut self) {
+        let rule = self.settings.keep_rule;
+        let extra: Vec<PathBuf> = self
+            .duplicate_groups
+            .iter()
+            .flat_map(|group| {
+                let best = group.best(rule);
+                group
+                    .files
+                    .iter()
+                    .enumerate()
+                    .filter(move |(index, _)| *index != best)
+                    .map(|(_, copy)| copy.file.path.clone())
+            })
+            .collect();
+        self.duplicate_moves = duplicates::plan_moves(&extra, &self.duplicates_folder());
+    }
+
+    fn show_duplicate_moves_window(&mut self, ctx: &egui::Context) {
+        if self.duplicate_moves.is_empty() {
+            return;
+        }
+        let mut is_open = true;
+        let mut confirmed = false;
+        let mut cancelled = false;
+        egui::Window::new("Move Extra Copies")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut is_open)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Move these copies to '{}'? They can be moved back from the report.",
+                    self.duplicates_folder().display()
+                ));
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("duplicate_moves_grid")
+                        .num_columns(2)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("Current Name");
+                            ui.strong("New Name");
+                            ui.end_row();
+                            for operation in &self.duplicate_moves {
+                                ui.label(
+                                    operation
+                                        .from
+                                        .file_name()
+                                        .unwrap_or_default()
+                                        .to_string_lossy(),
+                                )
+                                .on_hover_text(operation.from.to_string_lossy());
+                                ui.label(
+                                    operation
+                                        .to
+                                        .file_name()
+                                        .unwrap_or_default()
+                                        .to_string_lossy(),
+                                )
+                                .on_hover_text(operation.to.to_string_lossy());
+                                ui.end_row();
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    confirmed = ui.button("Move").clicked();
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+        if confirmed {
+            self.move_duplicates();
+        } else if cancelled || !is_open {
+            self.duplicate_moves.clear();
+        }
+    }
+
+    /// Moves the confirmed extra copies, drops them from the file list and
+    /// shows the outcome in the report, from where the moves can be undone.
+    fn move_duplicates(&mut self) {
+        let operations = std::mem::take(&mut self.duplicate_moves);
+        let before = (self.files.clone(), self.duplicate_groups.clone());
+        let outcomes = duplicates::move_files(&operations);
+        for outcome in outcomes
+            .iter()
+            .filter(|o| o.status == RenameStatus::Renamed)
+        {
+            self.files.retain(|file| file.path != outcome.old_path);
+            self.file_episode_inputs.remove(&outcome.old_path);
+        }
+        self.retain_present_duplicates();
+        let moved = outcomes
+            .iter()
+            .filter(|o| o.status == RenameStatus::Renamed)
+            .count();
+        let failed = outcomes.len() - moved;
+        self.duplicate_status = format!(
+            "Moved {} extra {} to '{}'.",
+            moved,
+            if moved == 1 { "copy" } else { "copies" },
+            self.duplicates_folder().display()
+        );
+        if failed > 0 {
+            self.duplicate_status
+                .push_str(&format!(" {} could not be moved, see the report.", failed));
+        }
+        self.show_rename_report(outcomes);
+        self.fetch_status = self.duplicate_status.clone();
+        self.duplicate_undo = Some(before);
+    }
+
+    /// Moves the copies of the last [`Self::move_duplicates`] back and lists
+    /// them again.
+    fn undo_duplicate_moves(&mut self) {
+        let Some((files, groups)) = self.duplicate_undo.take() else {
+            return;
+        };
+        let outcomes = rename::undo_renames(&self.rename_report);
+        for outcome in outcomes
+            .iter()
+            .filter(|o| o.status == RenameStatus::Renamed)
+        {
+            let restored = outcome.new_path.as_ref();
+            if let Some(file) = files.iter().find(|file| Some(&file.path) == restored) {
+                self.files.push(file.clone());
+            }
+        }
+        self.files
+            .sort_by(|a, b| natural_cmp(&a.path.to_string_lossy(), &b.path.to_string_lossy()));
+        self.duplicate_groups = groups;
+        self.retain_present_duplicates();
+        let restored = outcomes
+            .iter()
+            .filter(|o| o.status == RenameStatus::Renamed)
+            .count();
+        self.show_rename_report(outcomes);
+        self.duplicate_status = format!(
+            "Moved {} {} back.",
+            restored,
+            if restored == 1 { "copy" } else { "copies" }
+        );
+        self.fetch_status = self.duplicate_status.clone();
+    }
+
+    /// Drops copies that are no longer in the file list from the duplicates.
+    fn retain_present_duplicates(&mut self) {
+        let files = &self.files;
+        for group in &mut self.duplicate_groups {
+            group
+                .files
+                .retain(|copy| files.iter().any(|file| file.path == copy.file.path));
+        }
+        self.duplicate_groups.retain(|group| group.files.len() > 1);
+    }
+
+    /// Replaces all inputs with a sequential assignment. The inputs stay
+    /// editable, so single rows can be corrected afterwards.
+    fn assign_sequentially(&mut self) {
+        let skip = match plan::parse_episode_list(&self.sequential_skip) {
+            Ok(skip) => skip,
+            Err(e) => {
+                self.sequential_status = e;
+                return;
+            }
+        };
+        let assignments = plan::assign_sequentially(
+            &self.episodes,
+            &self.files,
+            self.sequential_start_after,
+            &skip,
+        );
+        let videos = self
+            .files
+            .iter()
+            .filter(|file| parse::is_video_file(&file.path))
+            .count();
+        self.sequential_status = if assignments.len() < videos {
+            format!(
+                "Assigned {} of {} video files, there are not enough episodes for the rest.",
+                assignments.len(),
+                videos
+            )
+        } else {
+            format!(
+                "Assigned {} video files. Check the result and correct single rows if needed.",
+                videos
+            )
+        };
+        self.file_episode_inputs = assignments;
+    }
+
+    /// This function contains the primary UI logic for manual assignment.
     fn assignment_ui(&mut self, ui: &mut egui::Ui) {
         ui.columns(2, |columns| {
             // --- Left Column: Episodes List (Reference) ---
             let left_ui = &mut columns[0];
             egui::Frame::group(left_ui.style()).show(left_ui, |ui| {
                 ui.heading("Episodes");
+                ui.horizontal(|ui| {
+                    ui.label("Sort by:");
+                    self.episodes_sort.header(ui, "Number", SortColumn::Episode);
+                    self.episodes_sort.header(ui, "Title", SortColumn::Title);
+                });
                 ui.separator();
 
+                let mut episodes: Vec<&Episode> = self.episodes.iter().collect();
+                let sort = self.episodes_sort;
+                episodes.sort_by(|a, b| {
+                    sort.apply(match sort.column {
+                        SortColumn::Title => natural_cmp(&a.title, &b.title),
+                        _ => episode_number_cmp(&a.episode, &b.episode),
+                    })
+                });
+
                 egui::ScrollArea::vertical()
                     .id_salt("episodes_scroll_area")
                     .show(ui, |ui| {
-                        for episode in &self.episodes {
+                        for episode in episodes {
                             ui.label(format!("E{}: {}", episode.episode, episode.title));
                             ui.separator();
                         }
@@ -477,31 +2674,352 @@ impl SeriesRenamer {
             let right_ui = &mut columns[1];
             egui::Frame::group(right_ui.style()).show(right_ui, |ui| {
                 ui.heading("Files");
+                ui.horizontal(|ui| {
+                    ui.label("Sort by:");
+                    self.files_sort.header(ui, "Name", SortColumn::FileName);
+                    self.files_sort
+                        .header(ui, "Assigned Episode", SortColumn::Episode);
+                });
+                self.sequential_ui(ui);
+                self.duplicates_ui(ui);
                 ui.separator();
 
+                // Count how often each episode number is typed to flag duplicates.
+                let input_counts = self.input_counts();
+                // Length of a regular episode, to flag files that are much longer or shorter.
+                let expected_duration = probe::expected_duration(self.episode_runtime, &self.files);
+
+                // Sort a list of indices so the inputs stay attached to their files.
+                let mut order: Vec<usize> = (0..self.files.len()).collect();
+                let sort = self.files_sort;
+                order.sort_by(|&a, &b| {
+                    let (a, b) = (&self.files[a], &self.files[b]);
+                    sort.apply(match sort.column {
+                        SortColumn::Episode => {
+                            let input = |file: &LocalFile| {
+                                self.file_episode_inputs
+                                    .get(&file.path)
+                                    .cloned()
+                                    .unwrap_or_default()
+                            };
+                            episode_number_cmp(&input(a), &input(b))
+                        }
+                        _ => natural_cmp(
+                            &a.path.file_name().unwrap_or_default().to_string_lossy(),
+                            &b.path.file_name().unwrap_or_default().to_string_lossy(),
+                        ),
+                    })
+                });
+
                 egui::ScrollArea::vertical()
                     .id_salt("files_scroll_area")
                     .show(ui, |ui| {
-                        for file in &self.files {
+                        for file in order.iter().map(|&index| &self.files[index]) {
                             ui.horizontal(|ui| {
                                 // Get the mutable string buffer for this file's input field.
                                 let buffer = self.file_episode_inputs.entry(file.path.clone()).or_default();
 
                                 // Show the text widget.
-                                ui.add(
+                                let input = ui.add(
                                     egui::TextEdit::singleline(buffer)
                                         .hint_text("Ep #")
                                         .desired_width(40.0)
                                 );
 
-                                // Show the filename next to the input.
-                                ui.label(file.path.file_name().unwrap().to_str().unwrap())
+                                // Show the filename next to the input, it also names
+                                // the input for screen readers.
+                                let label = ui
+                                    .label(file.path.file_name().unwrap().to_str().unwrap())
                                     .on_hover_text(file.path.to_str().unwrap());
+                                input.labelled_by(label.id);
+                                if let Some(media) = &file.media {
+                                    ui.weak(media.summary());
+                                }
                             });
+
+                            // Show the resulting name or the validation problem for this row.
+                            let input = self
+                                .file_episode_inputs
+                                .get(&file.path)
+                                .map(String::as_str)
+                                .unwrap_or("");
+                            match Self::input_status(&self.episodes, input, &input_counts) {
+                                InputStatus::Empty => {}
+                                InputStatus::Valid(episode) => {
+                                    match target_file_name(
+                                        self.season_number,
+                                        self.naming(),
+                                        episode,
+                                        file,
+                                    ) {
+                                        Ok(new_name) => ui.colored_label(
+                                            egui::Color32::from_rgb(90, 170, 90),
+                                            format!("-> {}", new_name),
+                                        ),
+                                        Err(e) => ui.colored_label(
+                                            ui.visuals().error_fg_color,
+                                            format!("Cannot rename: {}", e),
+                                        ),
+                                    };
+                                }
+                                InputStatus::Duplicate(episode) => {
+                                    ui.colored_label(
+                                        ui.visuals().warn_fg_color,
+                                        format!(
+                                            "-> {} (episode {} is assigned more than once)",
+                                            target_file_name(
+                                                self.season_number,
+                                                self.naming(),
+                                                episode,
+                                                file
+                                            )
+                                            .unwrap_or_else(|e| e),
+                                            episode.episode
+                                        ),
+                                    );
+                                }
+                                InputStatus::UnknownEpisode => {
+                                    ui.colored_label(
+                                        ui.visuals().error_fg_color,
+                                        format!("Unknown episode number '{}'", input.trim()),
+                                    );
+                                }
+                                InputStatus::InvalidCharacters => {
+                                    ui.colored_label(
+                                        ui.visuals().error_fg_color,
+                                        "Episode number may only contain digits",
+                                    );
+                                }
+                            }
+                            let duration = file.media.as_ref().and_then(|media| media.duration);
+                            if let Some(warning) =
+                                duration
+                                    .zip(expected_duration)
+                                    .and_then(|(duration, expected)| {
+                                        probe::duration_warning(duration, expected)
+                                    })
+                            {
+                                ui.colored_label(ui.visuals().warn_fg_color, warning);
+                            }
+                            // The title tag may name a different episode than the file name.
+                            if let Some(tag) =
+                                file.media.as_ref().and_then(|media| media.title.as_deref())
+                            {
+                                let tagged =
+                                    plan::episode_for_tag(&self.episodes, self.season_number, tag)
+                                        .map(|episode| episode.episode.trim().to_string())
+                                        .filter(|number| {
+                                            normalize_episode_number(input)
+                                                != normalize_episode_number(number)
+                                        });
+                                let is_assigned = !input.trim().is_empty();
+                                ui.horizontal(|ui| {
+                                    ui.weak(format!("Title tag: {}", tag));
+                                    let Some(number) = tagged else {
+                                        return;
+                                    };
+                                    if is_assigned {
+                                        ui.colored_label(
+                                            ui.visuals().warn_fg_color,
+                                            format!("The title tag names episode {}.", number),
+                                        );
+                                    }
+                                    if ui.small_button(format!("Use E{}", number)).clicked() {
+                                        self.file_episode_inputs.insert(file.path.clone(), number);
+                                    }
+                                });
+                            }
                             ui.separator();
                         }
                     });
             });
         });
     }
-}
\ No newline at end of file
+}
+
+const ALL_OR_NOTHING_HINT: &str =
+    "If any rename fails, undo the renames that were already applied.";
+
+const DUPLICATES_FOLDER_HINT: &str =
+    "Where extra copies of an episode are moved. Relative folders are inside the series directory.";
+
+const TITLE_TAGS_HINT: &str = "Store the episode title in renamed MKV and MP4 files, \
+     so players show it instead of an old title.";
+
+/// Episode titles of a rename plan by the current path of each file.
+fn plan_titles(plan: &[(Episode, LocalFile)]) -> HashMap<PathBuf, String> {
+    plan.iter()
+        .map(|(episode, file)| (file.path.clone(), episode.title.clone()))
+        .collect()
+}
+
+/// The renamed files among `outcomes` that support title tags, with the
+/// title from `titles` by their old path.
+fn title_tags(
+    titles: &HashMap<PathBuf, String>,
+    outcomes: &[RenameOutcome],
+) -> Vec<(PathBuf, String)> {
+    outcomes
+        .iter()
+        .filter(|o| o.status == RenameStatus::Renamed)
+        .filter_map(|outcome| {
+            let new_path = outcome
+                .new_path
+                .as_ref()
+                .filter(|path| probe::can_write_title(path))?;
+            let title = titles.get(&outcome.old_path)?;
+            Some((new_path.clone(), title.clone()))
+        })
+        .collect()
+}
+
+/// Stores the titles in the title tags of the files. Returns one message per
+/// file whose tag could not be written.
+fn write_title_tags(tags: &[(PathBuf, String)], on_status: &dyn Fn(String)) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (index, (path, title)) in tags.iter().enumerate() {
+        on_status(format!(
+            "Writing the title tag of {} of {} file(s)...",
+            index + 1,
+            tags.len()
+        ));
+        if let Err(e) = probe::write_title(path, title) {
+            log::warn!("Could not write the title tag of {}: {}", path.display(), e);
+            problems.push(format!(
+                "'{}': {}",
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                e
+            ));
+        }
+    }
+    problems
+}
+
+/// A text field for a directory with a button to pick it in a dialog.
+fn folder_input(ui: &mut egui::Ui, value: &mut String, hint: &str) {
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(value)
+                .hint_text(hint)
+                .desired_width(200.0),
+        );
+        if ui.button("...").on_hover_text("Browse").clicked() {
+            if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                *value = path.to_string_lossy().to_string();
+            }
+        }
+    });
+}
+
+/// Inputs for the naming options, shared by the settings and profile windows.
+fn naming_ui(ui: &mut egui::Ui, naming: &mut NamingOptions, id_salt: &str) {
+    egui::Grid::new((id_salt, "naming_grid"))
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Name Template:");
+            ui.add(
+                egui::TextEdit::singleline(&mut naming.template)
+                    .hint_text(template::DEFAULT_TEMPLATE)
+                    .desired_width(250.0),
+            )
+            .on_hover_text(format!(
+                "Placeholders: {}. Add a width like {{episode:02}} to pad numbers with zeros. \
+                 Release details like {{quality}} and {{group}} are read from the original \
+                 file name and left out with their brackets and separator if it has none.",
+                template::PLACEHOLDERS
+                    .iter()
+                    .map(|p| format!("{{{}}}", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            ui.end_row();
+
+            ui.label("Example:");
+            let release = parse::parse_release_info("Show.S01E02.1080p.WEB-DL.DDP5.1.x264-GRP.mkv");
+            let example = TemplateValues {
+                season: 1,
+                episode: "2",
+                title: "Title",
+                release: Some(&release),
+            };
+            let stem = naming
+                .policy
+                .sanitize(&template::render(&naming.template, &example));
+            ui.label(format!("{}.mkv", stem));
+            ui.end_row();
+
+            ui.label("Filename Policy:");
+            egui::ComboBox::from_id_salt((id_salt, "sanitize_policy"))
+                .selected_text(naming.policy.label())
+                .show_ui(ui, |ui| {
+                    for policy in SanitizePolicy::ALL {
+                        ui.selectable_value(&mut naming.policy, policy, policy.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Title Unicode:");
+            egui::ComboBox::from_id_salt((id_salt, "unicode_form"))
+                .selected_text(naming.unicode_form.label())
+                .show_ui(ui, |ui| {
+                    for form in UnicodeForm::ALL {
+                        ui.selectable_value(&mut naming.unicode_form, form, form.label());
+                    }
+                });
+            ui.end_row();
+        });
+}
+
+/// One line summary of applied renames, e.g. `Renamed 7 of 8 files (1 failed).`
+fn outcome_summary(outcomes: &[RenameOutcome]) -> String {
+    let failed = outcomes
+        .iter()
+        .filter(|o| o.status == RenameStatus::Failed)
+        .count();
+    format!(
+        "Renamed {} of {} files ({} failed).",
+        outcomes.len() - failed,
+        outcomes.len(),
+        failed
+    )
+}
+
+/// Scans the series directory, except for the duplicates folder, and fetches
+/// the season. Blocks, so it runs on a background thread. `on_status`
+/// receives progress messages like retries.
+fn fetch_season_and_files(
+    client: &omdb::Client,
+    imdb_link: &str,
+    series_dir: String,
+    duplicates_folder: &str,
+    season_number: u32,
+    cancel: &omdb::CancelToken,
+    on_status: &dyn Fn(String),
+) -> Result<FetchedSeason, String> {
+    let excluded = duplicates::resolve_folder(Path::new(&series_dir), duplicates_folder);
+    let files = scan::scan_directory_except(&series_dir, Some(&excluded));
+    let duplicate_groups = duplicates::find_duplicates(&files);
+    let Some(imdb_id) = scan::imdb_id_from_link(imdb_link) else {
+        return Err("Could not find IMDb ID in link.".to_string());
+    };
+    let on_retry = |retry: u32, error: &omdb::OmdbError| {
+        on_status(format!(
+            "{} Retrying ({}/{})...",
+            error, retry, client.policy.max_retries
+        ));
+    };
+    let episodes = client
+        .fetch_season(&imdb_id, season_number, cancel, &on_retry)
+        .map_err(|e| e.with_guidance())?;
+    // Only used to flag files of unusual length, so a failure is not fatal.
+    let runtime_minutes = client
+        .series_by_id(&imdb_id, cancel)
+        .ok()
+        .and_then(|series| series.runtime_minutes());
+    Ok(FetchedSeason {
+        episodes,
+        files,
+        runtime_minutes,
+        duplicates: duplicate_groups,
+    })
+}
@@ -0,0 +1,92 @@
+// src/app/batch.rs
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::duplicates::DuplicateGroup;
+use super::omdb::CancelToken;
+use super::plan;
+use super::rename::{self, RenameOutcome};
+use super::sanitize::NamingOptions;
+use super::{Episode, LocalFile};
+
+/// Where a job of the batch queue stands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    /// Fetching the season and scanning the directory, with the latest
+    /// progress message.
+    Fetching(String),
+    Failed(String),
+    /// Fetched and waiting to be reviewed.
+    Ready,
+    /// Reviewed, the plan is applied with the rest of the batch.
+    Reviewed,
+}
+
+impl JobStatus {
+    pub fn label(&self) -> String {
+        match self {
+            JobStatus::Fetching(status) if status.is_empty() => "Fetching...".to_string(),
+            JobStatus::Fetching(status) => status.clone(),
+            JobStatus::Failed(error) => format!("Failed: {}", error),
+            JobStatus::Ready => "Ready for review".to_string(),
+            JobStatus::Reviewed => "Reviewed".to_string(),
+        }
+    }
+}
+
+/// One series season in the batch queue.
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    /// Identifies the job in messages from its fetch thread.
+    pub id: u64,
+    pub imdb_link: String,
+    pub season_number: u32,
+    pub series_directory: String,
+    /// Naming options in effect when the job was added.
+    pub naming: NamingOptions,
+    pub status: JobStatus,
+    pub cancel: CancelToken,
+    pub episodes: Vec<Episode>,
+    pub files: Vec<LocalFile>,
+    /// Typical episode length from the provider.
+    pub runtime_minutes: Option<u32>,
+    /// Copies of the same episode among the files.
+    pub duplicates: Vec<DuplicateGroup>,
+    /// Episode numbers typed next to the files, kept between reviews.
+    pub inputs: HashMap<PathBuf, String>,
+    /// Confirmed assignments, set when the job is reviewed.
+    pub plan: Vec<(Episode, LocalFile)>,
+}
+
+impl BatchJob {
+    pub fn new(
+        id: u64,
+        imdb_link: String,
+        season_number: u32,
+        series_directory: String,
+        naming: NamingOptions,
+    ) -> Self {
+        Self {
+            id,
+            imdb_link,
+            season_number,
+            series_directory,
+            naming,
+            status: JobStatus::Fetching(String::new()),
+            cancel: CancelToken::default(),
+            episodes: Vec::new(),
+            files: Vec::new(),
+            runtime_minutes: None,
+            duplicates: Vec::new(),
+            inputs: HashMap::new(),
+            plan: Vec::new(),
+        }
+    }
+
+    /// Renames the files of a reviewed job.
+    pub fn apply(&self, all_or_nothing: bool) -> Vec<RenameOutcome> {
+        let (operations, failures) =
+            plan::plan_operations(&self.plan, self.season_number, &self.naming);
+        rename::apply_plan(&operations, failures, all_or_nothing)
+    }
+}
@@ -0,0 +1,384 @@
+// src/app/duplicates.rs
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::parse;
+use super::rename::{self, RenameOperation, RenameOutcome};
+use super::sorting::natural_cmp;
+use super::LocalFile;
+
+/// Folder extra copies are moved to if none is configured. Relative folders
+/// are inside the series directory.
+pub const DEFAULT_FOLDER: &str = "Duplicates";
+
+/// Bytes read from the start, middle and end of a file for its fingerprint.
+const SAMPLE_SIZE: u64 = 1024 * 1024;
+
+/// Which copy of an episode is kept. Ties are decided by the other rules,
+/// in the order listed here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum KeepRule {
+    #[default]
+    HigherResolution,
+    /// Prefer a PROPER or REPACK release, which fixes a broken one.
+    Proper,
+    Largest,
+}
+
+impl KeepRule {
+    pub const ALL: [KeepRule; 3] = [
+        KeepRule::HigherResolution,
+        KeepRule::Proper,
+        KeepRule::Largest,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeepRule::HigherResolution => "Prefer higher resolution",
+            KeepRule::Proper => "Prefer PROPER/REPACK",
+            KeepRule::Largest => "Keep largest",
+        }
+    }
+}
+
+/// One copy in a group of duplicates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateFile {
+    pub file: LocalFile,
+    pub size: u64,
+}
+
+impl DuplicateFile {
+    /// Vertical resolution from the container header, otherwise from the
+    /// file name.
+    fn resolution(&self) -> u32 {
+        if let Some(height) = self.file.media.as_ref().and_then(|media| media.height) {
+            return height;
+        }
+        let name = self
+            .file
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        parse::parse_release_info(&name)
+            .resolution
+            .trim_end_matches(['p', 'i'])
+            .parse()
+            .unwrap_or(0)
+    }
+
+    fn is_proper(&self) -> bool {
+        let name = self
+            .file
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        !parse::parse_release_info(&name).revision.is_empty()
+    }
+}
+
+/// Files that hold the same episode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// Episode the file names share, like `S01E03`. `None` if they were
+    /// only found by their content.
+    pub episode: Option<String>,
+    /// Whether all copies were compared byte by byte and are the same.
+    pub identical: bool,
+    /// Copies in natural name order.
+    pub files: Vec<DuplicateFile>,
+}
+
+impl DuplicateGroup {
+    pub fn label(&self) -> String {
+        match (&self.episode, self.identical) {
+            (Some(episode), true) => format!("{} (identical copies)", episode),
+            (Some(episode), false) => episode.clone(),
+            (None, _) => "Identical content".to_string(),
+        }
+    }
+
+    /// Index of the copy `rule` keeps. The first copy wins remaining ties.
+    pub fn best(&self, rule: KeepRule) -> usize {
+        let mut rules = vec![rule];
+        rules.extend(KeepRule::ALL.into_iter().filter(|other| *other != rule));
+        let key = |copy: &DuplicateFile| {
+            rules
+                .iter()
+                .map(|rule| match rule {
+                    KeepRule::HigherResolution => copy.resolution() as u64,
+                    KeepRule::Proper => copy.is_proper() as u64,
+                    KeepRule::Largest => copy.size,
+                })
+                .collect::<Vec<_>>()
+        };
+        (0..self.files.len())
+            .rev()
+            .max_by_key(|&index| key(&self.files[index]))
+            .unwrap_or(0)
+    }
+}
+
+/// Identifies a file's content from its size and samples of its start,
+/// middle and end, so large video files need not be read completely. Files
+/// with the same fingerprint are only likely to be equal, see
+/// [`same_content`].
+pub fn fingerprint(path: &Path) -> std::io::Result<u64> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hasher = DefaultHasher::new();
+    hasher.write_u64(size);
+    let mut buffer = vec![0; SAMPLE_SIZE.min(size) as usize];
+    for offset in [
+        0,
+        size.saturating_sub(SAMPLE_SIZE) / 2,
+        size.saturating_sub(SAMPLE_SIZE),
+    ] {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buffer)?;
+        hasher.write(&buffer);
+    }
+    Ok(hasher.finish())
+}
+
+/// Whether two files have the same content, comparing them completely.
+pub fn same_content(a: &Path, b: &Path) -> std::io::Result<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    if a.metadata()?.len() != b.metadata()?.len() {
+        return Ok(false);
+    }
+    let (mut buffer_a, mut buffer_b) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    loop {
+        let read = a.read(&mut buffer_a)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut buffer_b[..read])?;
+        if buffer_a[..read] != buffer_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Finds video files that hold the same episode: files whose names carry
+/// the same season and episode numbers, and files with the same content.
+/// Only files of equal size are fingerprinted, and only files with equal
+/// fingerprints are compared.
+pub fn find_duplicates(files: &[LocalFile]) -> Vec<DuplicateGroup> {
+    let copies: Vec<DuplicateFile> = files
+        .iter()
+        .filter(|file| parse::is_video_file(&file.path))
+        .filter_map(|file| {
+            let size = std::fs::metadata(&file.path).ok()?.len();
+            Some(DuplicateFile {
+                file: file.clone(),
+                size,
+            })
+        })
+        .collect();
+
+    // Every copy starts in its own group, copies found to match are merged.
+    let mut group_of: Vec<usize> = (0..copies.len()).collect();
+    fn root(group_of: &mut [usize], mut index: usize) -> usize {
+        while group_of[index] != index {
+            group_of[index] = group_of[group_of[index]];
+            index = group_of[index];
+        }
+        index
+    }
+    let mut merge = |indices: &[usize]| {
+        for pair in indices.windows(2) {
+            let (a, b) = (root(&mut group_of, pair[0]), root(&mut group_of, pair[1]));
+            group_of[a] = b;
+        }
+    };
+
+    let mut by_episode: HashMap<(u32, Vec<u32>), Vec<usize>> = HashMap::new();
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut episodes = Vec::new();
+    for (index, copy) in copies.iter().enumerate() {
+        let name = copy
+            .file
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let parsed =
+            parse::parse_episode_name(&name).map(|parsed| (parsed.season, parsed.episodes));
+        if let Some(key) = &parsed {
+            by_episode.entry(key.clone()).or_default().push(index);
+        }
+        episodes.push(parsed);
+        if copy.size > 0 {
+            by_size.entry(copy.size).or_default().push(index);
+        }
+    }
+    // The first copy of each distinct content, for every copy that was compared.
+    let mut content_of: Vec<Option<usize>> = vec![None; copies.len()];
+    for indices in by_size.values().filter(|indices| indices.len() > 1) {
+        let mut by_fingerprint: HashMap<u64, Vec<usize>> = HashMap::new();
+        for &index in indices {
+            match fingerprint(&copies[index].file.path) {
+                Ok(hash) => by_fingerprint.entry(hash).or_default().push(index),
+                Err(e) => log::debug!(
+                    "Could not read {}: {}",
+                    copies[index].file.path.display(),
+                    e
+                ),
+            }
+        }
+        for candidates in by_fingerprint
+            .values()
+            .filter(|candidates| candidates.len() > 1)
+        {
+            let mut originals: Vec<usize> = Vec::new();
+            for &index in candidates {
+                let path = &copies[index].file.path;
+                let original = originals.iter().copied().find(|&original| {
+                    same_content(&copies[original].file.path, path)
+                        .map_err(|e| log::debug!("Could not compare {}: {}", path.display(), e))
+                        .unwrap_or(false)
+                });
+                match original {
+                    Some(original) => {
+                        content_of[index] = Some(original);
+                        merge(&[original, index]);
+                    }
+                    None => {
+                        content_of[index] = Some(index);
+                        originals.push(index);
+                    }
+                }
+            }
+        }
+    }
+    by_episode.values().for_each(|indices| merge(indices));
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for index in 0..copies.len() {
+        members
+            .entry(root(&mut group_of, index))
+            .or_default()
+            .push(index);
+    }
+    let mut groups: Vec<DuplicateGroup> = members
+        .into_values()
+        .filter(|indices| indices.len() > 1)
+        .map(|indices| {
+            let first = &episodes[indices[0]];
+            let episode = first
+                .as_ref()
+                .filter(|_| indices.iter().all(|&index| episodes[index] == *first))
+                .map(|(season, numbers)| {
+                    let numbers: Vec<String> =
+                        numbers.iter().map(|n| format!("E{:02}", n)).collect();
+                    format!("S{:02}{}", season, numbers.concat())
+                });
+            let identical = content_of[indices[0]].is_some()
+                && indices
+                    .iter()
+                    .all(|&index| content_of[index] == content_of[indices[0]]);
+            let mut files: Vec<DuplicateFile> = indices
+                .into_iter()
+                .map(|index| copies[index].clone())
+                .collect();
+            files.sort_by(|a, b| {
+                natural_cmp(
+                    &a.file
+                        .path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy(),
+                    &b.file
+                        .path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy(),
+                )
+            });
+            DuplicateGroup {
+                episode,
+                identical,
+                files,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        natural_cmp(
+            &a.files[0].file.path.to_string_lossy(),
+            &b.files[0].file.path.to_string_lossy(),
+        )
+    });
+    groups
+}
+
+/// The folder extra copies of files in `series_directory` are moved to.
+pub fn resolve_folder(series_directory: &Path, folder: &str) -> PathBuf {
+    let folder = folder.trim();
+    let folder = if folder.is_empty() {
+        DEFAULT_FOLDER
+    } else {
+        folder
+    };
+    series_directory.join(folder)
+}
+
+/// Plans moving files into `folder`. A number is added to the name if the
+/// folder already holds a file of that name, or another file of the plan
+/// goes there.
+pub fn plan_moves(paths: &[PathBuf], folder: &Path) -> Vec<RenameOperation> {
+    let mut targets: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        let name = path.file_name().unwrap_or_default();
+        let stem = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let extension = path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        let mut target = folder.join(name);
+        let mut number = 2;
+        while target.exists() || targets.contains(&target) {
+            target = folder.join(format!("{} ({}){}", stem, number, extension));
+            number += 1;
+        }
+        targets.push(target);
+    }
+    paths
+        .iter()
+        .zip(targets)
+        .map(|(from, to)| RenameOperation {
+            from: from.clone(),
+            to,
+        })
+        .collect()
+}
+
+/// Moves the files of a plan from [`plan_moves`], creating the folders they
+/// go to. Existing files are never overwritten.
+pub fn move_files(operations: &[RenameOperation]) -> Vec<RenameOutcome> {
+    let mut outcomes = Vec::new();
+    let mut ready = Vec::new();
+    for operation in operations {
+        match operation.to.parent().map(std::fs::create_dir_all) {
+            Some(Err(e)) => outcomes.push(RenameOutcome::failed(
+                operation.from.clone(),
+                Some(operation.to.clone()),
+                format!("Could not create the folder: {}", e),
+            )),
+            _ => ready.push(operation.clone()),
+        }
+    }
+    outcomes.extend(rename::apply_renames(&ready));
+    outcomes
+}
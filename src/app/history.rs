@@ -0,0 +1,76 @@
+// src/app/history.rs
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many directory and series combinations are remembered.
+pub const MAX_ENTRIES: usize = 20;
+
+/// A directory processed with a series, as offered in the recent menus.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryEntry {
+    pub series_directory: String,
+    pub imdb_link: String,
+    pub season_number: u32,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Summary of what happened, e.g. `Renamed 8 of 8 files (0 failed).`
+    pub outcome: String,
+}
+
+/// Recently processed combinations, newest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Adds an entry at the front, replacing an older one for the same
+    /// directory and series, and forgets the oldest beyond [`MAX_ENTRIES`].
+    pub fn record(&mut self, entry: HistoryEntry) {
+        self.entries.retain(|e| {
+            e.series_directory != entry.series_directory || e.imdb_link != entry.imdb_link
+        });
+        self.entries.insert(0, entry);
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    /// The newest entry for each IMDb link.
+    pub fn series(&self) -> Vec<&HistoryEntry> {
+        let mut seen = Vec::new();
+        self.entries
+            .iter()
+            .filter(|e| {
+                let is_new = !seen.contains(&&e.imdb_link);
+                seen.push(&e.imdb_link);
+                is_new
+            })
+            .collect()
+    }
+}
+
+pub fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// How long ago `timestamp` was relative to `now`, e.g. `3 days ago`.
+pub fn age_label(timestamp: u64, now: u64) -> String {
+    let seconds = now.saturating_sub(timestamp);
+    let (count, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3_599 => (seconds / 60, "minute"),
+        3_600..=86_399 => (seconds / 3_600, "hour"),
+        86_400..=2_591_999 => (seconds / 86_400, "day"),
+        _ => (seconds / 2_592_000, "month"),
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
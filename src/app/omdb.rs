@@ -0,0 +1,384 @@
+// src/app/omdb.rs
+use serde::Deserialize;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::Episode;
+
+/// Page where users can request a free API key.
+pub const API_KEY_URL: &str = "https://www.omdbapi.com/apikey.aspx";
+
+/// Default server for metadata requests.
+pub const DEFAULT_BASE_URL: &str = "https://www.omdbapi.com/";
+
+/// A well known series used to check that an API key is accepted.
+const TEST_IMDB_ID: &str = "tt0903747";
+
+/// Why a request to OMDb did not return the expected data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OmdbError {
+    /// The key is unknown, not activated or missing.
+    InvalidApiKey(String),
+    /// The daily request limit of the key has been reached.
+    RateLimited(String),
+    /// The series or season does not exist on OMDb.
+    NotFound(String),
+    /// Any other error OMDb reported in the response body.
+    Api(String),
+    /// A non-success HTTP status without an OMDb error message.
+    Http { status: u16, status_text: String },
+    /// The request did not reach OMDb.
+    Network(String),
+    /// No response arrived within the configured timeout.
+    Timeout(Duration),
+    /// The user cancelled the request.
+    Cancelled,
+    /// The response was not the JSON we expected.
+    Parse(String),
+}
+
+impl OmdbError {
+    /// Maps the `Error` field of an OMDb response to a typed error.
+    fn from_message(message: String) -> Self {
+        let lower = message.to_lowercase();
+        if lower.contains("api key") {
+            OmdbError::InvalidApiKey(message)
+        } else if lower.contains("limit") {
+            OmdbError::RateLimited(message)
+        } else if lower.contains("not found") || lower.contains("incorrect imdb id") {
+            OmdbError::NotFound(message)
+        } else {
+            OmdbError::Api(message)
+        }
+    }
+
+    /// Whether trying the same request again may succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            OmdbError::Network(_) | OmdbError::Timeout(_) => true,
+            OmdbError::Http { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// What the user can do to fix the problem.
+    pub fn guidance(&self) -> &'static str {
+        match self {
+            OmdbError::InvalidApiKey(_) => {
+                "Check the key in Settings. \
+                 New keys must be activated through the link in the OMDb email."
+            }
+            OmdbError::RateLimited(_) => {
+                "The free key allows 1,000 requests per day. \
+                 Try again tomorrow or use a patron key."
+            }
+            OmdbError::NotFound(_) => {
+                "Check that the IMDb link points to the series (not an episode) \
+                 and that the season exists."
+            }
+            OmdbError::Api(_) | OmdbError::Http { .. } => {
+                "OMDb may be having problems. Try again later."
+            }
+            OmdbError::Network(_) | OmdbError::Timeout(_) => {
+                "Check your internet connection, or raise the timeout and retries in Settings."
+            }
+            OmdbError::Cancelled => "",
+            OmdbError::Parse(_) => "OMDb returned an unexpected response. Try again later.",
+        }
+    }
+
+    /// The error followed by the [`guidance`](Self::guidance) on its own
+    /// line, if there is any.
+    pub fn with_guidance(&self) -> String {
+        match self.guidance() {
+            "" => self.to_string(),
+            guidance => format!("{}\n{}", self, guidance),
+        }
+    }
+}
+
+impl fmt::Display for OmdbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OmdbError::InvalidApiKey(message) => write!(f, "Invalid API key: {}", message),
+            OmdbError::RateLimited(message) => write!(f, "Rate limit reached: {}", message),
+            OmdbError::NotFound(message) => write!(f, "Not found: {}", message),
+            OmdbError::Api(message) => write!(f, "API Error: {}", message),
+            OmdbError::Http {
+                status,
+                status_text,
+            } => write!(f, "API Error: {} {}", status, status_text),
+            OmdbError::Network(message) => write!(f, "Network Error: {}", message),
+            OmdbError::Timeout(timeout) => {
+                write!(
+                    f,
+                    "Network Error: no response within {} seconds",
+                    timeout.as_secs()
+                )
+            }
+            OmdbError::Cancelled => write!(f, "Cancelled"),
+            OmdbError::Parse(message) => write!(f, "JSON Parse Error: {}", message),
+        }
+    }
+}
+
+/// How long to wait for a response and how often to try again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub timeout: Duration,
+    /// Number of additional attempts after the first one failed.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further retry.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    fn delay_before_retry(&self, retry: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+    }
+}
+
+/// Shared flag that aborts an in-flight request when set.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// How often a waiting request checks whether it was cancelled.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Sleeps for `duration`, returning early with an error if cancelled.
+fn wait(duration: Duration, cancel: &CancelToken) -> Result<(), OmdbError> {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        if cancel.is_cancelled() {
+            return Err(OmdbError::Cancelled);
+        }
+        std::thread::sleep(CANCEL_POLL_INTERVAL.min(deadline - Instant::now()));
+    }
+    Ok(())
+}
+
+/// Performs a single request, giving up after the timeout.
+fn fetch_once(
+    url: &str,
+    timeout: Duration,
+    cancel: &CancelToken,
+) -> Result<ehttp::Response, OmdbError> {
+    let (sender, receiver) = crossbeam_channel::bounded(1);
+    ehttp::fetch(ehttp::Request::get(url), move |result| {
+        let _ = sender.send(result);
+    });
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if cancel.is_cancelled() {
+            return Err(OmdbError::Cancelled);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(OmdbError::Timeout(timeout));
+        }
+        match receiver.recv_timeout(CANCEL_POLL_INTERVAL.min(remaining)) {
+            Ok(result) => {
+                let response = result.map_err(OmdbError::Network)?;
+                check_response(&response)?;
+                return Ok(response);
+            }
+            Err(crossbeam_channel::RecvTimeoutError::Timeout) => {}
+            Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
+                return Err(OmdbError::Network("Request was dropped".to_string()));
+            }
+        }
+    }
+}
+
+/// Performs a request, retrying transient failures with exponential backoff.
+/// `on_retry` is called with the retry number and the error before each retry.
+fn fetch_with_retry(
+    url: &str,
+    policy: &RetryPolicy,
+    cancel: &CancelToken,
+    on_retry: &dyn Fn(u32, &OmdbError),
+) -> Result<ehttp::Response, OmdbError> {
+    let mut retry = 0;
+    loop {
+        match fetch_once(url, policy.timeout, cancel) {
+            Err(e) if e.is_transient() && retry < policy.max_retries => {
+                retry += 1;
+                on_retry(retry, &e);
+                wait(policy.delay_before_retry(retry), cancel)?;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Fields OMDb includes in every response.
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+struct StatusResponse {
+    #[serde(default)]
+    pub response: String,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+struct SeasonResponse {
+    #[serde(default)]
+    pub episodes: Vec<Episode>,
+}
+
+/// A series as returned by a title or ID lookup.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Series {
+    #[serde(rename = "Title", default)]
+    pub title: String,
+    #[serde(rename = "imdbID", default)]
+    pub imdb_id: String,
+    /// Typical episode length like `47 min`, or `N/A`.
+    #[serde(rename = "Runtime", default)]
+    pub runtime: String,
+}
+
+impl Series {
+    /// The typical episode length in minutes, if OMDb knows it.
+    pub fn runtime_minutes(&self) -> Option<u32> {
+        self.runtime.trim().strip_suffix("min")?.trim().parse().ok()
+    }
+}
+
+/// Checks the OMDb status fields of a response. OMDb reports most errors
+/// with `"Response":"False"` and an `Error` message, often with HTTP 200.
+fn check_response(response: &ehttp::Response) -> Result<(), OmdbError> {
+    match serde_json::from_slice::<StatusResponse>(&response.bytes) {
+        Ok(status) if status.response == "False" => Err(OmdbError::from_message(
+            status.error.unwrap_or_else(|| "Unknown error".to_string()),
+        )),
+        _ if !response.ok => Err(OmdbError::Http {
+            status: response.status,
+            status_text: response.status_text.clone(),
+        }),
+        Ok(_) => Ok(()),
+        Err(e) => Err(OmdbError::Parse(e.to_string())),
+    }
+}
+
+/// Connection details for an OMDb compatible server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Client {
+    /// Base URL requests are sent to, e.g. `https://www.omdbapi.com/`.
+    pub base_url: String,
+    pub api_key: String,
+    pub policy: RetryPolicy,
+}
+
+impl Client {
+    /// Builds the request URL for the given query parameters.
+    fn request_url(&self, params: &[(&str, &str)]) -> String {
+        let separator = if self.base_url.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        let query: Vec<String> = params
+            .iter()
+            .chain(std::iter::once(&("apikey", self.api_key.as_str())))
+            .map(|(name, value)| format!("{}={}", name, encode_query_value(value)))
+            .collect();
+        format!("{}{}{}", self.base_url, separator, query.join("&"))
+    }
+
+    /// Fetches the episode list of a season. Blocks until done, so call it
+    /// from a background thread.
+    pub fn fetch_season(
+        &self,
+        imdb_id: &str,
+        season_number: u32,
+        cancel: &CancelToken,
+        on_retry: &dyn Fn(u32, &OmdbError),
+    ) -> Result<Vec<Episode>, OmdbError> {
+        let season = season_number.to_string();
+        let request_url = self.request_url(&[("i", imdb_id), ("Season", &season)]);
+        let response = fetch_with_retry(&request_url, &self.policy, cancel, on_retry)?;
+        serde_json::from_slice::<SeasonResponse>(&response.bytes)
+            .map(|season| season.episodes)
+            .map_err(|e| OmdbError::Parse(e.to_string()))
+    }
+
+    /// Looks up the series whose title best matches `title`. Blocks until
+    /// done, so call it from a background thread.
+    pub fn find_series(&self, title: &str, cancel: &CancelToken) -> Result<Series, OmdbError> {
+        self.fetch_series(&[("t", title), ("type", "series")], cancel)
+    }
+
+    /// Looks up the title of a series. Blocks until done, so call it from a
+    /// background thread.
+    pub fn series_by_id(&self, imdb_id: &str, cancel: &CancelToken) -> Result<Series, OmdbError> {
+        self.fetch_series(&[("i", imdb_id)], cancel)
+    }
+
+    fn fetch_series(
+        &self,
+        params: &[(&str, &str)],
+        cancel: &CancelToken,
+    ) -> Result<Series, OmdbError> {
+        let request_url = self.request_url(params);
+        let response = fetch_with_retry(&request_url, &self.policy, cancel, &|_, _| {})?;
+        serde_json::from_slice::<Series>(&response.bytes)
+            .map_err(|e| OmdbError::Parse(e.to_string()))
+    }
+
+    /// Sends a small request and reports whether OMDb accepts the API key.
+    /// Blocks until done, so call it from a background thread.
+    pub fn test_api_key(&self) -> Result<(), OmdbError> {
+        let request_url = self.request_url(&[("i", TEST_IMDB_ID)]);
+        fetch_with_retry(
+            &request_url,
+            &self.policy,
+            &CancelToken::default(),
+            &|_, _| {},
+        )
+        .map(|_| ())
+    }
+}
+
+/// Checks that a base URL can be used for requests. Returns a description of
+/// the problem otherwise.
+pub fn validate_base_url(base_url: &str) -> Result<(), String> {
+    let rest = base_url
+        .strip_prefix("https://")
+        .or_else(|| base_url.strip_prefix("http://"))
+        .ok_or_else(|| "The base URL must start with https:// or http://.".to_string())?;
+    let host = rest.split(['/', '?']).next().unwrap_or("");
+    if host.is_empty() || base_url.chars().any(char::is_whitespace) {
+        return Err("The base URL must contain a host name and no spaces.".to_string());
+    }
+    Ok(())
+}
+
+/// Percent-encodes everything except unreserved characters.
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,442 @@
+// src/app/parse.rs
+use std::path::Path;
+
+/// Extensions of files that are treated as episodes.
+pub const VIDEO_EXTENSIONS: &[&str] = &[
+    "mkv", "mp4", "m4v", "avi", "mov", "wmv", "ts", "webm", "mpg", "mpeg",
+];
+
+/// Extensions download clients use while a file is still being written.
+const PARTIAL_EXTENSIONS: &[&str] = &["part", "partial", "!qb", "crdownload", "download", "tmp"];
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+pub fn is_video_file(path: &Path) -> bool {
+    VIDEO_EXTENSIONS.contains(&extension(path).as_str())
+}
+
+/// Whether the file is still being downloaded, judging by its name.
+pub fn is_partial_download(path: &Path) -> bool {
+    PARTIAL_EXTENSIONS.contains(&extension(path).as_str())
+}
+
+/// How the episode number was written in a file name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpisodePattern {
+    /// `S01E02`, the form used by nearly all release names.
+    SeasonEpisode,
+    /// `1x02`
+    Cross,
+    /// `Season 1 Episode 2`
+    Words,
+}
+
+/// Series, season and episode read from a file name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedName {
+    /// Text in front of the episode number with separators replaced by
+    /// spaces. Empty if the name starts with the episode number.
+    pub series: String,
+    pub season: u32,
+    /// One entry per episode, more for files like `S01E01E02`.
+    pub episodes: Vec<u32>,
+    pub pattern: EpisodePattern,
+}
+
+/// Reads series, season and episode from a file name like
+/// `Show.Name.S01E02.720p.mkv`. Returns `None` if no episode number is found.
+pub fn parse_episode_name(file_name: &str) -> Option<ParsedName> {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let lower = stem.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+
+    let (start, season, episodes, pattern) = (0..bytes.len())
+        .filter(|&i| i == 0 || !bytes[i - 1].is_ascii_alphanumeric())
+        .find_map(|i| {
+            season_episode_at(bytes, i)
+                .map(|(season, episodes)| (i, season, episodes, EpisodePattern::SeasonEpisode))
+        })
+        .or_else(|| {
+            (0..bytes.len())
+                .filter(|&i| i == 0 || !bytes[i - 1].is_ascii_alphanumeric())
+                .find_map(|i| {
+                    cross_at(bytes, i)
+                        .map(|(season, episode)| (i, season, vec![episode], EpisodePattern::Cross))
+                })
+        })
+        .or_else(|| {
+            words(&lower)
+                .map(|(i, season, episode)| (i, season, vec![episode], EpisodePattern::Words))
+        })?;
+
+    Some(ParsedName {
+        series: clean_series_name(&stem[..start]),
+        season,
+        episodes,
+        pattern,
+    })
+}
+
+/// Reads up to `max` digits starting at `i`. Fails if there are none or more
+/// than `max`.
+fn digits_at(bytes: &[u8], i: usize, max: usize) -> Option<(u32, usize)> {
+    let count = bytes[i.min(bytes.len())..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    if count == 0 || count > max {
+        return None;
+    }
+    let value = std::str::from_utf8(&bytes[i..i + count])
+        .ok()?
+        .parse()
+        .ok()?;
+    Some((value, i + count))
+}
+
+/// Matches `s01e02`, `s01e02e03` and `s01e02-e04` at `i`.
+fn season_episode_at(bytes: &[u8], i: usize) -> Option<(u32, Vec<u32>)> {
+    if bytes.get(i) != Some(&b's') {
+        return None;
+    }
+    let (season, next) = digits_at(bytes, i + 1, 2)?;
+    if bytes.get(next) != Some(&b'e') {
+        return None;
+    }
+    let (first, mut next) = digits_at(bytes, next + 1, 3)?;
+    let mut episodes = vec![first];
+    loop {
+        let (is_range, digits_start) = match (bytes.get(next), bytes.get(next + 1)) {
+            (Some(b'e'), _) => (false, next + 1),
+            (Some(b'-'), Some(b'e')) => (true, next + 2),
+            _ => break,
+        };
+        let Some((episode, after)) = digits_at(bytes, digits_start, 3) else {
+            break;
+        };
+        let last = *episodes.last().unwrap();
+        if is_range && episode > last + 1 {
+            episodes.extend(last + 1..episode);
+        }
+        if episode > last {
+            episodes.push(episode);
+        }
+        next = after;
+    }
+    Some((season, episodes))
+}
+
+/// Matches `1x02` at `i` when it is not part of a longer word.
+fn cross_at(bytes: &[u8], i: usize) -> Option<(u32, u32)> {
+    let (season, next) = digits_at(bytes, i, 2)?;
+    if bytes.get(next) != Some(&b'x') {
+        return None;
+    }
+    let (episode, end) = digits_at(bytes, next + 1, 3)?;
+    if end - next < 3 || bytes.get(end).is_some_and(|b| b.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some((season, episode))
+}
+
+/// Matches `season 1 episode 2` with any separators. Returns the position
+/// of `season` along with the numbers.
+fn words(lower: &str) -> Option<(usize, u32, u32)> {
+    let tokens: Vec<(usize, &str)> = lower
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| (token.as_ptr() as usize - lower.as_ptr() as usize, token))
+        .collect();
+    tokens.windows(4).find_map(|window| {
+        let [(start, season_word), (_, season), (_, episode_word), (_, episode)] = window else {
+            return None;
+        };
+        if *season_word != "season" || !matches!(*episode_word, "episode" | "ep") {
+            return None;
+        }
+        Some((*start, season.parse().ok()?, episode.parse().ok()?))
+    })
+}
+
+fn clean_series_name(prefix: &str) -> String {
+    prefix
+        .replace(['.', '_'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches([' ', '-', '[', '('])
+        .to_string()
+}
+
+/// Release details read from a file name like
+/// `Show.S01E01.1080p.WEB-DL.DDP5.1.x264-GRP.mkv`, written the way release
+/// names usually spell them. Details that are not found are empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReleaseInfo {
+    /// `1080p`
+    pub resolution: String,
+    /// `WEB-DL`
+    pub source: String,
+    /// `x264`
+    pub video_codec: String,
+    /// `HDR10`, `DV`
+    pub hdr: String,
+    /// `DDP5.1 Atmos`
+    pub audio: String,
+    /// Release group, `GRP`
+    pub group: String,
+    /// `PROPER`, `REPACK`
+    pub revision: String,
+}
+
+impl ReleaseInfo {
+    /// Resolution and source, e.g. `1080p WEB-DL`.
+    pub fn quality(&self) -> String {
+        [self.resolution.as_str(), self.source.as_str()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+const RESOLUTIONS: &[(&str, &str)] = &[
+    ("2160p", "2160p"),
+    ("4k", "2160p"),
+    ("uhd", "2160p"),
+    ("1080p", "1080p"),
+    ("1080i", "1080i"),
+    ("720p", "720p"),
+    ("576p", "576p"),
+    ("480p", "480p"),
+];
+
+/// Sources, some of them written as two tokens like `WEB-DL`.
+const SOURCES: &[(&[&str], &str)] = &[
+    (&["web", "dl"], "WEB-DL"),
+    (&["webdl"], "WEB-DL"),
+    (&["web", "rip"], "WEBRip"),
+    (&["webrip"], "WEBRip"),
+    (&["web"], "WEB"),
+    (&["blu", "ray"], "BluRay"),
+    (&["bluray"], "BluRay"),
+    (&["bdrip"], "BDRip"),
+    (&["brrip"], "BRRip"),
+    (&["remux"], "Remux"),
+    (&["hdtv"], "HDTV"),
+    (&["pdtv"], "PDTV"),
+    (&["dvdrip"], "DVDRip"),
+    (&["dvd"], "DVD"),
+];
+
+const VIDEO_CODECS: &[(&[&str], &str)] = &[
+    (&["x264"], "x264"),
+    (&["x265"], "x265"),
+    (&["h264"], "H.264"),
+    (&["h", "264"], "H.264"),
+    (&["h265"], "H.265"),
+    (&["h", "265"], "H.265"),
+    (&["hevc"], "HEVC"),
+    (&["avc"], "AVC"),
+    (&["av1"], "AV1"),
+    (&["vp9"], "VP9"),
+    (&["xvid"], "XviD"),
+];
+
+const HDR_FORMATS: &[(&[&str], &str)] = &[
+    (&["hdr10plus"], "HDR10+"),
+    (&["hdr10"], "HDR10"),
+    (&["hdr"], "HDR"),
+    (&["dolby", "vision"], "DV"),
+    (&["dovi"], "DV"),
+    (&["dv"], "DV"),
+];
+
+/// Audio formats, longest first since they may be followed by the channels
+/// like `DDP5.1`.
+const AUDIO_FORMATS: &[(&str, &str)] = &[
+    ("truehd", "TrueHD"),
+    ("atmos", "Atmos"),
+    ("dtshd", "DTS-HD"),
+    ("eac3", "EAC3"),
+    ("flac", "FLAC"),
+    ("opus", "Opus"),
+    ("ddp", "DDP"),
+    ("aac", "AAC"),
+    ("ac3", "AC3"),
+    ("dts", "DTS"),
+    ("dd", "DD"),
+];
+
+const REVISIONS: &[(&str, &str)] = &[("proper", "PROPER"), ("repack", "REPACK")];
+
+/// Whether a token is an episode number like `s01e02` or `1x02`.
+fn is_episode_token(token: &str) -> bool {
+    let bytes = token.as_bytes();
+    season_episode_at(bytes, 0).is_some() || cross_at(bytes, 0).is_some()
+}
+
+/// Whether a token after a dash continues the episode number, like the
+/// `E02` of `S01E01-E02`.
+fn is_episode_part(token: &str) -> bool {
+    let lower = token.to_ascii_lowercase();
+    let is_episode = lower
+        .strip_prefix('e')
+        .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()));
+    is_episode || is_episode_token(&lower)
+}
+
+/// The first entry of `table` whose tokens start at `tokens[i]`, with the
+/// number of tokens it spans.
+fn match_tokens(
+    tokens: &[&str],
+    i: usize,
+    table: &[(&[&str], &'static str)],
+) -> Option<(&'static str, usize)> {
+    table.iter().find_map(|(words, name)| {
+        let matches = tokens
+            .get(i..i + words.len())
+            .is_some_and(|found| found == *words);
+        matches.then_some((*name, words.len()))
+    })
+}
+
+/// An audio format at `tokens[i]` with the channels, which are split over
+/// two tokens like `DDP5.1` or three like `TrueHD.7.1`.
+fn audio_at(tokens: &[&str], i: usize) -> Option<(String, usize)> {
+    let token = tokens[i];
+    let (prefix, name) = AUDIO_FORMATS
+        .iter()
+        .find(|(prefix, _)| token.starts_with(prefix))?;
+    let is_digit = |i: usize| {
+        tokens
+            .get(i)
+            .is_some_and(|t| t.len() == 1 && t.as_bytes()[0].is_ascii_digit())
+    };
+    match &token[prefix.len()..] {
+        "" if is_digit(i + 1) && is_digit(i + 2) => {
+            Some((format!("{} {}.{}", name, tokens[i + 1], tokens[i + 2]), 3))
+        }
+        "" => Some((name.to_string(), 1)),
+        channels if channels.len() == 1 && channels.as_bytes()[0].is_ascii_digit() => {
+            if is_digit(i + 1) {
+                Some((format!("{}{}.{}", name, channels, tokens[i + 1]), 2))
+            } else {
+                Some((format!("{}{}", name, channels), 1))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The release group: the word after the last `-`, as in `x264-GRP`, or a
+/// leading `[Group]` as in fansub releases. A dash between spaces separates
+/// words of the name instead.
+fn release_group(stem: &str) -> String {
+    let tail = stem
+        .rsplit_once('-')
+        .filter(|(head, tail)| !head.ends_with(' ') && !tail.starts_with(' '))
+        // Some releases carry an extra tag like `-GRP[rarbg]`.
+        .map(|(_, tail)| tail.split('[').next().unwrap_or_default().trim_end());
+    if let Some(group) = tail.filter(|group| {
+        !group.is_empty()
+            && group.chars().all(|c| c.is_ascii_alphanumeric())
+            && !group.chars().all(|c| c.is_ascii_digit())
+            && !["dl", "rip", "ray", "hd"].contains(&group.to_ascii_lowercase().as_str())
+            && !is_episode_part(group)
+    }) {
+        return group.to_string();
+    }
+    stem.strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map(|(group, _)| group.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Reads resolution, source, codecs, HDR format, group and revision from a
+/// release file name. Only the part after the episode number is searched
+/// for tokens, so series names like `The Web` are not mistaken for them.
+pub fn parse_release_info(file_name: &str) -> ReleaseInfo {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let lower = stem.to_ascii_lowercase();
+    let all: Vec<&str> = lower
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .collect();
+    let tokens = match all.iter().position(|token| is_episode_token(token)) {
+        Some(episode) => &all[episode + 1..],
+        None => &all[..],
+    };
+
+    let mut info = ReleaseInfo {
+        group: release_group(&stem),
+        ..ReleaseInfo::default()
+    };
+    let (mut audio, mut hdr, mut revisions) = (Vec::new(), Vec::new(), Vec::new());
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        let mut length = 1;
+        if let Some((_, name)) = RESOLUTIONS.iter().find(|(word, _)| *word == token) {
+            if info.resolution.is_empty() {
+                info.resolution = name.to_string();
+            }
+        } else if let Some((name, span)) = match_tokens(tokens, i, SOURCES) {
+            if info.source.is_empty() {
+                info.source = name.to_string();
+            }
+            length = span;
+        } else if let Some((name, span)) = match_tokens(tokens, i, VIDEO_CODECS) {
+            if info.video_codec.is_empty() {
+                info.video_codec = name.to_string();
+            }
+            length = span;
+        } else if let Some((name, span)) = match_tokens(tokens, i, HDR_FORMATS) {
+            if !hdr.contains(&name) {
+                hdr.push(name);
+            }
+            length = span;
+        } else if let Some((_, name)) = REVISIONS.iter().find(|(word, _)| *word == token) {
+            revisions.push(*name);
+        } else if let Some((name, span)) = audio_at(tokens, i) {
+            if !audio.contains(&name) {
+                audio.push(name);
+            }
+            length = span;
+        }
+        i += length;
+    }
+    info.hdr = hdr.join(" ");
+    info.audio = audio.join(" ");
+    info.revision = revisions.join(" ");
+    info
+}
+
+/// Reduces a series title to lowercase words, so `Show.Name.2019` and
+/// `Show Name (2019)` compare equal. A trailing year is dropped, since
+/// release names often leave it out.
+pub fn normalize_title(title: &str) -> String {
+    let ascii = deunicode::deunicode(title).to_lowercase();
+    let mut words: Vec<&str> = ascii
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.len() > 1
+        && words.last().is_some_and(|w| {
+            w.len() == 4 && w.parse::<u32>().is_ok_and(|y| (1900..2100).contains(&y))
+        })
+    {
+        words.pop();
+    }
+    words.join(" ")
+}
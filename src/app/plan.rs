@@ -0,0 +1,302 @@
+// src/app/plan.rs
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::parse;
+use super::rename::{RenameOperation, RenameOutcome};
+use super::sanitize::NamingOptions;
+use super::sorting::{episode_number_cmp, natural_cmp};
+use super::template::{self, TemplateValues};
+use super::{Episode, LocalFile};
+
+/// Files assigned to episodes, plus every input that could not be used.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenamePlan {
+    /// Assignments sorted by episode number.
+    pub entries: Vec<(Episode, LocalFile)>,
+    pub issues: Vec<String>,
+}
+
+/// Normalizes an episode number so that inputs like `01` or ` 1 ` match `1`.
+/// Returns `None` if the input is not a plain number.
+pub fn normalize_episode_number(input: &str) -> Option<String> {
+    let input = input.trim();
+    if input.is_empty() || !input.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let trimmed = input.trim_start_matches('0');
+    Some(if trimmed.is_empty() { "0" } else { trimmed }.to_string())
+}
+
+/// Computes the new filename for a file assigned to the given episode. The
+/// rendered name goes through the policy as a whole, so text from the
+/// template is held to the same rules as the title. A title too long for the
+/// target is shortened; fails if the name still does not fit or would be
+/// empty.
+pub fn target_file_name(
+    season_number: u32,
+    naming: &NamingOptions,
+    episode: &Episode,
+    file: &LocalFile,
+) -> Result<String, String> {
+    let extension = file.path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    let sanitized_title = naming.title(&episode.title);
+    let release =
+        parse::parse_release_info(&file.path.file_name().unwrap_or_default().to_string_lossy());
+    let render = |title: &str| {
+        let values = TemplateValues {
+            season: season_number,
+            episode: episode.episode.trim(),
+            title,
+            release: Some(&release),
+        };
+        naming
+            .policy
+            .sanitize(&template::render(&naming.template, &values))
+    };
+    let policy = naming.policy;
+    let mut stem = render(&sanitized_title);
+    let excess = policy
+        .length_of(&stem)
+        .saturating_sub(policy.max_stem_length(extension, file.path.parent()));
+    if excess > 0 {
+        // Only the title is shortened, the episode number has to stay.
+        let length = policy.length_of(&sanitized_title).saturating_sub(excess);
+        stem = render(&policy.truncate(&sanitized_title, length));
+    }
+    policy.finalize_file_name(&stem, extension, file.path.parent())
+}
+
+/// Episode numbers given as single numbers and ranges, like `3, 7-9`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpisodeList {
+    /// Inclusive ranges, a single number is a range of one.
+    ranges: Vec<(u32, u32)>,
+}
+
+impl EpisodeList {
+    /// Whether the list contains the episode number, which may be written
+    /// like `01`.
+    pub fn contains(&self, number: &str) -> bool {
+        let Some(number) = normalize_episode_number(number).and_then(|n| n.parse::<u32>().ok())
+        else {
+            return false;
+        };
+        self.ranges
+            .iter()
+            .any(|(first, last)| (*first..=*last).contains(&number))
+    }
+}
+
+/// Reads a list of episode numbers like `3, 7-9`. Returns a message naming
+/// the first part that is not a number or range.
+pub fn parse_episode_list(text: &str) -> Result<EpisodeList, String> {
+    let mut list = EpisodeList::default();
+    // Join spaced ranges like `7 - 9` before splitting at the spaces.
+    let text = text.split('-').map(str::trim).collect::<Vec<_>>().join("-");
+    for part in text
+        .split([',', ' ', ';'])
+        .filter(|part| !part.trim().is_empty())
+    {
+        let invalid = || {
+            format!(
+                "'{}' is not an episode number or a range like 7-9.",
+                part.trim()
+            )
+        };
+        let parse = |n: &str| normalize_episode_number(n).and_then(|n| n.parse::<u32>().ok());
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        match (parse(first), parse(last)) {
+            (Some(first), Some(last)) if first <= last => list.ranges.push((first, last)),
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(list)
+}
+
+/// Assigns video files in natural name order to episodes in number order,
+/// for releases that are sorted correctly but named arbitrarily. Episodes up
+/// to number `start_after` and the episodes in `skip` are left out. Returns
+/// the episode number for each assigned file; files beyond the last episode
+/// get none.
+pub fn assign_sequentially(
+    episodes: &[Episode],
+    files: &[LocalFile],
+    start_after: u32,
+    skip: &EpisodeList,
+) -> HashMap<PathBuf, String> {
+    let mut episodes: Vec<&Episode> = episodes.iter().collect();
+    episodes.sort_by(|a, b| episode_number_cmp(&a.episode, &b.episode));
+    let episodes = episodes.into_iter().filter(|episode| {
+        let number = normalize_episode_number(&episode.episode).and_then(|n| n.parse::<u32>().ok());
+        // Episodes without a plain number are only used from the start.
+        number.map_or(start_after == 0, |number| number > start_after)
+            && !skip.contains(&episode.episode)
+    });
+
+    let mut files: Vec<&LocalFile> = files
+        .iter()
+        .filter(|file| parse::is_video_file(&file.path))
+        .collect();
+    files.sort_by(|a, b| {
+        natural_cmp(
+            &a.path.file_name().unwrap_or_default().to_string_lossy(),
+            &b.path.file_name().unwrap_or_default().to_string_lossy(),
+        )
+    });
+
+    files
+        .into_iter()
+        .zip(episodes)
+        .map(|(file, episode)| (file.path.clone(), episode.episode.trim().to_string()))
+        .collect()
+}
+
+/// The episode a title tag refers to. The tag may hold the episode title,
+/// possibly after the series and episode number, or a release name with the
+/// episode number of the given season. Returns `None` if the tag matches no
+/// episode or several.
+pub fn episode_for_tag<'a>(
+    episodes: &'a [Episode],
+    season_number: u32,
+    tag: &str,
+) -> Option<&'a Episode> {
+    let tag_title = parse::normalize_title(tag);
+    if let Some(episode) = episodes
+        .iter()
+        .find(|episode| parse::normalize_title(&episode.title) == tag_title)
+    {
+        return Some(episode);
+    }
+    let mut matches = episodes.iter().filter(|episode| {
+        let title = parse::normalize_title(&episode.title);
+        !title.is_empty() && tag_title.ends_with(&format!(" {}", title))
+    });
+    if let Some(first) = matches.next() {
+        return matches.next().is_none().then_some(first);
+    }
+    let parsed = parse::parse_episode_name(&format!("{}.mkv", tag))?;
+    match parsed.episodes[..] {
+        [number] if parsed.season == season_number => episodes
+            .iter()
+            .find(|episode| normalize_episode_number(&episode.episode) == Some(number.to_string())),
+        _ => None,
+    }
+}
+
+/// Builds the rename plan from the episode numbers typed next to each file.
+pub fn build_rename_plan(
+    episodes: &[Episode],
+    files: &[LocalFile],
+    inputs: &HashMap<PathBuf, String>,
+    season_number: u32,
+    naming: &NamingOptions,
+) -> RenamePlan {
+    let mut plan = RenamePlan::default();
+
+    // Create a quick lookup map from normalized episode number to the Episode struct.
+    let episode_map: HashMap<String, &Episode> = episodes
+        .iter()
+        .map(|e| {
+            let key = normalize_episode_number(&e.episode).unwrap_or_else(|| e.episode.clone());
+            (key, e)
+        })
+        .collect();
+
+    // Remembers which file claimed an episode first, to report duplicates.
+    let mut assigned: HashMap<String, &LocalFile> = HashMap::new();
+
+    for file in files {
+        // Get the user's input for the current file.
+        let Some(input) = inputs.get(&file.path) else {
+            continue;
+        };
+        if input.trim().is_empty() {
+            continue;
+        }
+        let file_name = file.path.file_name().unwrap_or_default().to_string_lossy();
+
+        let Some(number) = normalize_episode_number(input) else {
+            plan.issues.push(format!(
+                "'{}': '{}' is not a valid episode number.",
+                file_name,
+                input.trim()
+            ));
+            continue;
+        };
+        let Some(episode) = episode_map.get(&number) else {
+            plan.issues.push(format!(
+                "'{}': episode {} does not exist in season {}.",
+                file_name, number, season_number
+            ));
+            continue;
+        };
+        if let Some(first) = assigned.get(&number) {
+            plan.issues.push(format!(
+                "'{}': episode {} is already assigned to '{}', this file will be ignored.",
+                file_name,
+                number,
+                first.path.file_name().unwrap_or_default().to_string_lossy()
+            ));
+            continue;
+        }
+        if let Err(e) = target_file_name(season_number, naming, episode, file) {
+            plan.issues.push(format!("'{}': {}.", file_name, e));
+            continue;
+        }
+
+        // We found a match, add it to the plan.
+        assigned.insert(number, file);
+        plan.entries.push(((*episode).clone(), file.clone()));
+    }
+
+    plan.entries
+        .sort_by(|(a, _), (b, _)| episode_number_cmp(&a.episode, &b.episode));
+    plan
+}
+
+/// Turns plan entries into file operations. Files for which no target path
+/// can be computed are returned as failed outcomes instead.
+pub fn plan_operations(
+    entries: &[(Episode, LocalFile)],
+    season_number: u32,
+    naming: &NamingOptions,
+) -> (Vec<RenameOperation>, Vec<RenameOutcome>) {
+    let mut operations = Vec::new();
+    let mut failures = Vec::new();
+    for (episode, file) in entries {
+        let original_path = &file.path;
+        if original_path.extension().is_none() {
+            failures.push(RenameOutcome::failed(
+                original_path.clone(),
+                None,
+                "Could not get file extension",
+            ));
+        } else if episode.episode.parse::<u32>().is_err() {
+            failures.push(RenameOutcome::failed(
+                original_path.clone(),
+                None,
+                format!("Could not parse episode number '{}'", episode.episode),
+            ));
+        } else if let Some(parent_dir) = original_path.parent() {
+            match target_file_name(season_number, naming, episode, file) {
+                Ok(new_name) => operations.push(RenameOperation {
+                    from: original_path.clone(),
+                    to: parent_dir.join(new_name),
+                }),
+                Err(e) => failures.push(RenameOutcome::failed(
+                    original_path.clone(),
+                    None,
+                    format!("Could not name the file: {}", e),
+                )),
+            }
+        } else {
+            failures.push(RenameOutcome::failed(
+                original_path.clone(),
+                None,
+                "Could not get parent directory",
+            ));
+        }
+    }
+    (operations, failures)
+}
@@ -0,0 +1,966 @@
+// src/app/probe.rs
+//! Reads duration, resolution, codecs and the title tag from the headers of
+//! Matroska (MKV, WebM) and MP4 files without decoding anything, and writes
+//! the title tag.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use super::LocalFile;
+
+/// Technical details read from the header of a video file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MediaInfo {
+    pub duration: Option<Duration>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub video_codec: Option<String>,
+    /// One entry per distinct audio codec.
+    pub audio_codecs: Vec<String>,
+    /// Title stored in the container, which players show instead of the
+    /// file name.
+    pub title: Option<String>,
+}
+
+impl MediaInfo {
+    /// Short description for the file list, e.g. `44:12, 1920x1080, H.264, AAC`.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(duration) = self.duration {
+            let seconds = duration.as_secs();
+            parts.push(if seconds >= 3600 {
+                format!(
+                    "{}:{:02}:{:02}",
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60
+                )
+            } else {
+                format!("{}:{:02}", seconds / 60, seconds % 60)
+            });
+        }
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            parts.push(format!("{}x{}", width, height));
+        }
+        parts.extend(self.video_codec.clone());
+        parts.extend(self.audio_codecs.iter().cloned());
+        parts.join(", ")
+    }
+
+    fn add_audio_codec(&mut self, codec: String) {
+        if !self.audio_codecs.contains(&codec) {
+            self.audio_codecs.push(codec);
+        }
+    }
+}
+
+/// Largest header element that is read into memory.
+const MAX_HEADER_SIZE: u64 = 64 * 1024 * 1024;
+
+pub fn probe_file(path: &Path) -> Result<MediaInfo, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    probe(&mut BufReader::new(file))
+}
+
+/// Reads the media info from a Matroska or MP4 stream.
+pub fn probe<R: Read + Seek>(reader: &mut R) -> Result<MediaInfo, String> {
+    let mut magic = [0u8; 8];
+    reader
+        .read_exact(&mut magic)
+        .map_err(|_| "The file is too short.".to_string())?;
+    reader.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+    if magic[..4] == EBML_ID.to_be_bytes() {
+        probe_matroska(reader)
+    } else if &magic[4..8] == b"ftyp" {
+        probe_mp4(reader)
+    } else {
+        Err("Not a Matroska or MP4 file.".to_string())
+    }
+}
+
+// --- Matroska ---
+
+const EBML_ID: u32 = 0x1A45_DFA3;
+const DOC_TYPE: u32 = 0x4282;
+const SEGMENT: u32 = 0x1853_8067;
+const SEEK_HEAD: u32 = 0x114D_9B74;
+const SEEK: u32 = 0x4DBB;
+const SEEK_ID: u32 = 0x53AB;
+const SEEK_POSITION: u32 = 0x53AC;
+const INFO: u32 = 0x1549_A966;
+const TIMESTAMP_SCALE: u32 = 0x2A_D7B1;
+const DURATION: u32 = 0x4489;
+const TITLE: u32 = 0x7BA9;
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_TYPE: u32 = 0x83;
+const CODEC_ID: u32 = 0x86;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const CLUSTER: u32 = 0x1F43_B675;
+const VOID: u32 = 0xEC;
+const CRC_32: u32 = 0xBF;
+
+/// Reads a variable length integer. Returns the value without the length
+/// marker (with it for IDs) and its length in bytes.
+fn read_vint<R: Read>(reader: &mut R, keep_marker: bool) -> Result<Option<(u64, usize)>, String> {
+    let mut first = [0u8; 1];
+    match reader.read(&mut first) {
+        Ok(0) => return Ok(None),
+        Ok(_) => {}
+        Err(e) => return Err(e.to_string()),
+    }
+    let length = first[0].leading_zeros() as usize + 1;
+    if length > 8 {
+        return Err("Invalid Matroska element header.".to_string());
+    }
+    let mut value = if keep_marker {
+        first[0] as u64
+    } else {
+        first[0] as u64 & (0xFF >> length)
+    };
+    let mut rest = [0u8; 7];
+    reader
+        .read_exact(&mut rest[..length - 1])
+        .map_err(|e| e.to_string())?;
+    for byte in &rest[..length - 1] {
+        value = (value << 8) | *byte as u64;
+    }
+    Ok(Some((value, length)))
+}
+
+/// Reads an element ID and size. The size is `None` if it is unknown, as
+/// with live streams.
+fn read_element_header<R: Read>(reader: &mut R) -> Result<Option<(u32, Option<u64>)>, String> {
+    let Some((id, id_length)) = read_vint(reader, true)? else {
+        return Ok(None);
+    };
+    if id_length > 4 {
+        return Err("Invalid Matroska element ID.".to_string());
+    }
+    let Some((size, size_length)) = read_vint(reader, false)? else {
+        return Err("The file ends inside an element header.".to_string());
+    };
+    let unknown = size == (1 << (7 * size_length)) - 1;
+    Ok(Some((id as u32, (!unknown).then_some(size))))
+}
+
+fn read_payload<R: Read>(reader: &mut R, size: Option<u64>) -> Result<Vec<u8>, String> {
+    let size = size
+        .filter(|size| *size <= MAX_HEADER_SIZE)
+        .ok_or_else(|| "A Matroska header element is too large.".to_string())?;
+    let mut payload = vec![0u8; size as usize];
+    reader.read_exact(&mut payload).map_err(|e| e.to_string())?;
+    Ok(payload)
+}
+
+/// Splits an element payload into its child elements. Stops at the first
+/// malformed child.
+fn ebml_children(data: &[u8]) -> Vec<(u32, &[u8])> {
+    split_ebml(data).0
+}
+
+/// Like [`ebml_children`], but `None` unless the children make up the whole
+/// payload. Used before an element is re-encoded, so nothing is dropped.
+fn all_ebml_children(data: &[u8]) -> Option<Vec<(u32, &[u8])>> {
+    let (children, rest) = split_ebml(data);
+    rest.is_empty().then_some(children)
+}
+
+/// The children up to the first malformed one, and the bytes from there on.
+fn split_ebml(mut data: &[u8]) -> (Vec<(u32, &[u8])>, &[u8]) {
+    let mut children = Vec::new();
+    while !data.is_empty() {
+        let mut cursor = std::io::Cursor::new(data);
+        let Ok(Some((id, Some(size)))) = read_element_header(&mut cursor) else {
+            break;
+        };
+        let start = cursor.position() as usize;
+        let Some(end) = usize::try_from(size)
+            .ok()
+            .and_then(|size| start.checked_add(size))
+        else {
+            break;
+        };
+        let Some(payload) = data.get(start..end) else {
+            break;
+        };
+        children.push((id, payload));
+        data = &data[end..];
+    }
+    (children, data)
+}
+
+fn ebml_uint(data: &[u8]) -> u64 {
+    data.iter()
+        .take(8)
+        .fold(0, |value, byte| (value << 8) | *byte as u64)
+}
+
+fn ebml_float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f32::from_be_bytes(data.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(data.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+fn ebml_string(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+/// A header element of a Matroska segment and where it is in the file.
+struct HeaderElement {
+    start: u64,
+    end: u64,
+    payload: Vec<u8>,
+}
+
+/// Finds the Info and Tracks elements of the segment.
+fn find_matroska_header<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<(Option<HeaderElement>, Option<HeaderElement>), String> {
+    let Some((EBML_ID, size)) = read_element_header(reader)? else {
+        return Err("Not a Matroska file.".to_string());
+    };
+    let header = read_payload(reader, size)?;
+    let doc_type = ebml_children(&header)
+        .into_iter()
+        .find(|(id, _)| *id == DOC_TYPE)
+        .map(|(_, data)| ebml_string(data))
+        .unwrap_or_default();
+    if doc_type != "matroska" && doc_type != "webm" {
+        return Err(format!(
+            "Unsupported Matroska document type '{}'.",
+            doc_type
+        ));
+    }
+    let Some((SEGMENT, segment_size)) = read_element_header(reader)? else {
+        return Err("The Matroska file contains no segment.".to_string());
+    };
+    let segment_start = reader.stream_position().map_err(|e| e.to_string())?;
+    let segment_end = segment_size.map(|size| segment_start.saturating_add(size));
+
+    // The header elements usually come before the first cluster. If not, the
+    // seek head says where they are.
+    let mut info = None;
+    let mut tracks = None;
+    let mut seeks = Vec::new();
+    while info.is_none() || tracks.is_none() {
+        let position = reader.stream_position().map_err(|e| e.to_string())?;
+        if segment_end.is_some_and(|end| position >= end) {
+            break;
+        }
+        let Some((id, size)) = read_element_header(reader)? else {
+            break;
+        };
+        match id {
+            INFO | TRACKS => {
+                let payload = read_payload(reader, size)?;
+                let end = reader.stream_position().map_err(|e| e.to_string())?;
+                let element = Some(HeaderElement {
+                    start: position,
+                    end,
+                    payload,
+                });
+                if id == INFO {
+                    info = element;
+                } else {
+                    tracks = element;
+                }
+            }
+            SEEK_HEAD => {
+                let payload = read_payload(reader, size)?;
+                for (_, seek) in ebml_children(&payload)
+                    .into_iter()
+                    .filter(|(id, _)| *id == SEEK)
+                {
+                    let children = ebml_children(seek);
+                    let field = |wanted| {
+                        children
+                            .iter()
+                            .find(|(id, _)| *id == wanted)
+                            .map(|(_, data)| ebml_uint(data))
+                    };
+                    if let (Some(id), Some(position)) = (field(SEEK_ID), field(SEEK_POSITION)) {
+                        seeks.push((id as u32, position));
+                    }
+                }
+            }
+            CLUSTER => break,
+            _ => {
+                let Some(size) = size else {
+                    break;
+                };
+                let offset = i64::try_from(size).map_err(|e| e.to_string())?;
+                reader
+                    .seek(SeekFrom::Current(offset))
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    for (id, position) in seeks {
+        let target = match id {
+            INFO if info.is_none() => &mut info,
+            TRACKS if tracks.is_none() => &mut tracks,
+            _ => continue,
+        };
+        let start = segment_start.saturating_add(position);
+        reader
+            .seek(SeekFrom::Start(start))
+            .map_err(|e| e.to_string())?;
+        if let Some((found, size)) = read_element_header(reader)? {
+            if found == id {
+                let payload = read_payload(reader, size)?;
+                let end = reader.stream_position().map_err(|e| e.to_string())?;
+                *target = Some(HeaderElement {
+                    start,
+                    end,
+                    payload,
+                });
+            }
+        }
+    }
+    Ok((info, tracks))
+}
+
+fn probe_matroska<R: Read + Seek>(reader: &mut R) -> Result<MediaInfo, String> {
+    let (info, tracks) = find_matroska_header(reader)?;
+    let mut media = MediaInfo::default();
+    if let Some(info) = info {
+        let children = ebml_children(&info.payload);
+        let scale = children
+            .iter()
+            .find(|(id, _)| *id == TIMESTAMP_SCALE)
+            .map_or(1_000_000, |(_, data)| ebml_uint(data));
+        media.duration = children
+            .iter()
+            .find(|(id, _)| *id == DURATION)
+            .and_then(|(_, data)| ebml_float(data))
+            .filter(|duration| duration.is_finite() && *duration > 0.0)
+            .map(|duration| Duration::from_nanos((duration * scale as f64) as u64));
+        media.title = children
+            .iter()
+            .find(|(id, _)| *id == TITLE)
+            .map(|(_, data)| ebml_string(data))
+            .filter(|title| !title.trim().is_empty());
+    }
+    for (_, entry) in tracks
+        .iter()
+        .flat_map(|tracks| ebml_children(&tracks.payload))
+        .filter(|(id, _)| *id == TRACK_ENTRY)
+    {
+        let children = ebml_children(entry);
+        let field = |wanted| {
+            children
+                .iter()
+                .find(|(id, _)| *id == wanted)
+                .map(|(_, data)| *data)
+        };
+        let codec = field(CODEC_ID).map(ebml_string).unwrap_or_default();
+        match field(TRACK_TYPE).map(ebml_uint) {
+            Some(1) if media.video_codec.is_none() => {
+                media.video_codec = Some(matroska_codec_name(&codec));
+                if let Some(video) = field(VIDEO) {
+                    let video = ebml_children(video);
+                    let dimension = |wanted| {
+                        video
+                            .iter()
+                            .find(|(id, _)| *id == wanted)
+                            .map(|(_, data)| ebml_uint(data) as u32)
+                    };
+                    media.width = dimension(PIXEL_WIDTH);
+                    media.height = dimension(PIXEL_HEIGHT);
+                }
+            }
+            Some(2) => media.add_audio_codec(matroska_codec_name(&codec)),
+            _ => {}
+        }
+    }
+    Ok(media)
+}
+
+fn matroska_codec_name(codec_id: &str) -> String {
+    match codec_id {
+        "V_MPEG4/ISO/AVC" => "H.264",
+        "V_MPEGH/ISO/HEVC" => "HEVC",
+        "V_AV1" => "AV1",
+        "V_VP8" => "VP8",
+        "V_VP9" => "VP9",
+        "V_MPEG2" => "MPEG-2",
+        "A_AAC" => "AAC",
+        "A_AC3" => "AC-3",
+        "A_EAC3" => "E-AC-3",
+        "A_DTS" => "DTS",
+        "A_TRUEHD" => "TrueHD",
+        "A_OPUS" => "Opus",
+        "A_VORBIS" => "Vorbis",
+        "A_FLAC" => "FLAC",
+        "A_MPEG/L3" => "MP3",
+        other => other.split_once('_').map_or(other, |(_, name)| name),
+    }
+    .to_string()
+}
+
+// --- MP4 ---
+
+/// Child boxes by type, with their payloads.
+type Boxes<'a> = Vec<([u8; 4], &'a [u8])>;
+
+/// Splits a box payload into its child boxes. Stops at the first
+/// malformed child.
+fn mp4_boxes(data: &[u8]) -> Boxes<'_> {
+    split_mp4(data).0
+}
+
+/// Like [`mp4_boxes`], but `None` unless the boxes make up all of `data`.
+fn all_mp4_boxes(data: &[u8]) -> Option<Boxes<'_>> {
+    let (boxes, rest) = split_mp4(data);
+    rest.is_empty().then_some(boxes)
+}
+
+/// The boxes up to the first malformed one, and the bytes from there on.
+fn split_mp4(mut data: &[u8]) -> (Boxes<'_>, &[u8]) {
+    let mut boxes = Vec::new();
+    while data.len() >= 8 {
+        let size = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as u64;
+        let kind = [data[4], data[5], data[6], data[7]];
+        let (header, size) = match size {
+            0 => (8, data.len() as u64),
+            1 if data.len() >= 16 => (16, u64::from_be_bytes(data[8..16].try_into().unwrap())),
+            size => (8, size),
+        };
+        if size < header || size > data.len() as u64 {
+            break;
+        }
+        boxes.push((kind, &data[header as usize..size as usize]));
+        data = &data[size as usize..];
+    }
+    (boxes, data)
+}
+
+fn mp4_child<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    mp4_boxes(data)
+        .into_iter()
+        .find(|(k, _)| k == kind)
+        .map(|(_, payload)| payload)
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn be_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+/// A box at the top level of an MP4 file.
+struct TopLevelBox {
+    kind: [u8; 4],
+    start: u64,
+    header_size: u64,
+    size: u64,
+}
+
+/// Reads the headers of the top level boxes.
+fn top_level_boxes<R: Read + Seek>(reader: &mut R) -> Result<Vec<TopLevelBox>, String> {
+    let end = reader.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+    let mut boxes = Vec::new();
+    let mut position = 0;
+    while end - position >= 8 {
+        reader
+            .seek(SeekFrom::Start(position))
+            .map_err(|e| e.to_string())?;
+        let mut header = [0u8; 16];
+        reader
+            .read_exact(&mut header[..8])
+            .map_err(|e| e.to_string())?;
+        let (header_size, size) = match u32::from_be_bytes(header[..4].try_into().unwrap()) {
+            0 => (8, end - position),
+            1 => {
+                reader
+                    .read_exact(&mut header[8..])
+                    .map_err(|e| e.to_string())?;
+                (16, u64::from_be_bytes(header[8..].try_into().unwrap()))
+            }
+            size => (8, size as u64),
+        };
+        if size < header_size {
+            return Err("Invalid MP4 box.".to_string());
+        }
+        boxes.push(TopLevelBox {
+            kind: [header[4], header[5], header[6], header[7]],
+            start: position,
+            header_size,
+            size,
+        });
+        match position.checked_add(size) {
+            Some(next) if next <= end => position = next,
+            // Only the last box may be cut short, as in a file still being written.
+            _ => break,
+        }
+    }
+    Ok(boxes)
+}
+
+fn probe_mp4<R: Read + Seek>(reader: &mut R) -> Result<MediaInfo, String> {
+    // The movie box may come after the media data.
+    let moov = top_level_boxes(reader)?
+        .into_iter()
+        .find(|b| &b.kind == b"moov")
+        .ok_or_else(|| "The MP4 file contains no movie header.".to_string())?;
+    reader
+        .seek(SeekFrom::Start(moov.start + moov.header_size))
+        .map_err(|e| e.to_string())?;
+    let payload = read_payload(reader, Some(moov.size - moov.header_size))?;
+    Ok(parse_moov(&payload))
+}
+
+fn parse_moov(moov: &[u8]) -> MediaInfo {
+    let mut media = MediaInfo::default();
+    if let Some(mvhd) = mp4_child(moov, b"mvhd") {
+        let (timescale, duration) = if mvhd.first() == Some(&1) {
+            (
+                be_u32(mvhd, 20),
+                mvhd.get(24..32)
+                    .map(|d| u64::from_be_bytes(d.try_into().unwrap())),
+            )
+        } else {
+            (be_u32(mvhd, 12), be_u32(mvhd, 16).map(u64::from))
+        };
+        media.duration = timescale
+            .zip(duration)
+            .filter(|(timescale, duration)| {
+                *timescale > 0 && *duration != u32::MAX as u64 && *duration != u64::MAX
+            })
+            .and_then(|(timescale, duration)| {
+                Duration::try_from_secs_f64(duration as f64 / timescale as f64).ok()
+            });
+    }
+    for (_, trak) in mp4_boxes(moov)
+        .into_iter()
+        .filter(|(kind, _)| kind == b"trak")
+    {
+        let Some(mdia) = mp4_child(trak, b"mdia") else {
+            continue;
+        };
+        let handler = mp4_child(mdia, b"hdlr").and_then(|hdlr| hdlr.get(8..12));
+        let Some(entry) = mp4_child(mdia, b"minf")
+            .and_then(|minf| mp4_child(minf, b"stbl"))
+            .and_then(|stbl| mp4_child(stbl, b"stsd"))
+            .and_then(|stsd| stsd.get(8..))
+        else {
+            continue;
+        };
+        let Some(format) = entry.get(4..8) else {
+            continue;
+        };
+        match handler {
+            Some(b"vide") if media.video_codec.is_none() => {
+                media.video_codec = Some(mp4_codec_name(format));
+                media.width = be_u16(entry, 32).map(u32::from);
+                media.height = be_u16(entry, 34).map(u32::from);
+            }
+            Some(b"soun") => media.add_audio_codec(mp4_codec_name(format)),
+            _ => {}
+        }
+    }
+    media.title = mp4_child(moov, b"udta")
+        .and_then(|udta| mp4_child(udta, b"meta"))
+        .and_then(|meta| mp4_child(meta_children(meta).1, b"ilst"))
+        .and_then(|ilst| mp4_child(ilst, TITLE_ITEM))
+        .and_then(|item| mp4_child(item, b"data"))
+        .and_then(|data| data.get(8..))
+        .map(|title| {
+            String::from_utf8_lossy(title)
+                .trim_end_matches('\0')
+                .to_string()
+        })
+        .filter(|title| !title.trim().is_empty());
+    media
+}
+
+/// Item of the iTunes metadata list holding the title.
+const TITLE_ITEM: &[u8; 4] = b"\xA9nam";
+
+/// Splits a `meta` box into its version and flags and its children. Files
+/// written by QuickTime leave out the version and flags.
+fn meta_children(meta: &[u8]) -> (&[u8], &[u8]) {
+    if meta.get(4..8) == Some(b"hdlr") {
+        (&[], meta)
+    } else {
+        meta.split_at(meta.len().min(4))
+    }
+}
+
+fn mp4_codec_name(format: &[u8]) -> String {
+    match format {
+        b"avc1" | b"avc3" => "H.264".to_string(),
+        b"hvc1" | b"hev1" => "HEVC".to_string(),
+        b"av01" => "AV1".to_string(),
+        b"vp09" => "VP9".to_string(),
+        b"mp4v" => "MPEG-4".to_string(),
+        b"mp4a" => "AAC".to_string(),
+        b"ac-3" => "AC-3".to_string(),
+        b"ec-3" => "E-AC-3".to_string(),
+        b"Opus" => "Opus".to_string(),
+        b"fLaC" => "FLAC".to_string(),
+        other => String::from_utf8_lossy(other).trim().to_string(),
+    }
+}
+
+// --- Title tags ---
+
+/// Whether [`write_title`] supports the file, judging by its extension.
+pub fn can_write_title(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| ["mkv", "webm", "mp4", "m4v"].contains(&e.as_str()))
+}
+
+/// Stores `title` as the title tag of a Matroska or MP4 file, which players
+/// show instead of the file name. The header keeps its size, so this fails
+/// if it has no room for a longer title. The new header is checked before
+/// the file is patched in place, which keeps hard links, ownership and the
+/// modification time.
+pub fn write_title(path: &Path, title: &str) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    let metadata = file.metadata().map_err(|e| e.to_string())?;
+    let modified = metadata.modified().ok();
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)
+        .map_err(|_| "The file is too short.".to_string())?;
+    file.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+    let edit = if magic[..4] == EBML_ID.to_be_bytes() {
+        let edit = matroska_title_edit(&mut file, title)?;
+        let children = all_ebml_children(&edit.bytes).unwrap_or_default();
+        let ids: Vec<u32> = children.iter().map(|(id, _)| *id).collect();
+        let fits = matches!(ids.as_slice(), [INFO] | [INFO, VOID]) && edit.fits(metadata.len());
+        fits.then_some(edit)
+    } else if &magic[4..8] == b"ftyp" {
+        let edit = mp4_title_edit(&mut file, title)?;
+        let boxes = all_mp4_boxes(&edit.bytes).unwrap_or_default();
+        let kinds: Vec<&[u8; 4]> = boxes.iter().map(|(kind, _)| kind).collect();
+        let fits =
+            matches!(kinds.as_slice(), [b"moov"] | [b"moov", b"free"]) && edit.fits(metadata.len());
+        fits.then_some(edit)
+    } else {
+        return Err("Not a Matroska or MP4 file.".to_string());
+    };
+    let edit = edit.ok_or_else(|| "The new file header could not be built.".to_string())?;
+
+    let result = (|| -> std::io::Result<()> {
+        file.seek(SeekFrom::Start(edit.offset))?;
+        file.write_all(&edit.bytes)?;
+        if let Some(len) = edit.len {
+            file.set_len(len)?;
+        }
+        file.sync_all()?;
+        if let Some(modified) = modified {
+            file.set_modified(modified)?;
+        }
+        Ok(())
+    })();
+    result.map_err(|e| e.to_string())
+}
+
+/// A rewritten header: `bytes` replace the `replaced` bytes of the file from
+/// `offset` on, and the file is cut to `len` if set.
+struct HeaderEdit {
+    offset: u64,
+    replaced: u64,
+    bytes: Vec<u8>,
+    len: Option<u64>,
+}
+
+impl HeaderEdit {
+    /// Whether the replaced bytes lie within the file and the new bytes take
+    /// exactly their space, so nothing after them moves. Only an edit that
+    /// cuts the file at its end may change the size.
+    fn fits(&self, file_len: u64) -> bool {
+        let inside = self
+            .offset
+            .checked_add(self.replaced)
+            .is_some_and(|end| end <= file_len);
+        inside && (self.len.is_some() || self.bytes.len() as u64 == self.replaced)
+    }
+}
+
+fn no_room() -> String {
+    "The file header has no room for the title.".to_string()
+}
+
+/// The header is not rewritten unless all of it was understood, as parts
+/// that were not would be lost.
+fn malformed() -> String {
+    "The file header could not be read completely.".to_string()
+}
+
+/// Encodes an EBML element, with a size field of `size_length` bytes.
+fn encode_element(id: u32, payload: &[u8], size_length: usize) -> Vec<u8> {
+    let mut bytes: Vec<u8> = id
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|b| *b == 0)
+        .collect();
+    let size = (payload.len() as u64) | (1 << (7 * size_length));
+    bytes.extend_from_slice(&size.to_be_bytes()[8 - size_length..]);
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+/// Shortest size field for a payload of `size` bytes.
+fn size_length(size: usize) -> usize {
+    (1..8)
+        .find(|length| (size as u64) < (1 << (7 * length)) - 1)
+        .unwrap_or(8)
+}
+
+/// Rewrites the Info element with the new title. It has to fit into the
+/// space of the old element and any Void element after it.
+fn matroska_title_edit<R: Read + Seek>(file: &mut R, title: &str) -> Result<HeaderEdit, String> {
+    let (info, _) = find_matroska_header(&mut BufReader::new(&mut *file))?;
+    let info = info.ok_or_else(|| "The Matroska file contains no segment info.".to_string())?;
+
+    // The checksum would no longer match and padding inside is free space.
+    let mut payload = Vec::new();
+    let mut has_title = false;
+    for (id, data) in all_ebml_children(&info.payload).ok_or_else(malformed)? {
+        match id {
+            VOID | CRC_32 => {}
+            TITLE if !has_title => {
+                has_title = true;
+                payload.extend(encode_element(
+                    TITLE,
+                    title.as_bytes(),
+                    size_length(title.len()),
+                ));
+            }
+            _ => payload.extend(encode_element(id, data, size_length(data.len()))),
+        }
+    }
+    if !has_title {
+        payload.extend(encode_element(
+            TITLE,
+            title.as_bytes(),
+            size_length(title.len()),
+        ));
+    }
+
+    let mut room = info.end - info.start;
+    let end = file.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(info.end))
+        .map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(&mut *file);
+    if let Ok(Some((VOID, Some(size)))) = read_element_header(&mut reader) {
+        let void_end = reader
+            .stream_position()
+            .map_err(|e| e.to_string())?
+            .checked_add(size);
+        // A Void element claiming more than the file holds is not free space.
+        if let Some(void_end) = void_end.filter(|void_end| *void_end <= end) {
+            room = void_end - info.start;
+        }
+    }
+    // A longer size field leaves the remainder large enough for a Void
+    // element, which needs at least two bytes.
+    let mut element = Vec::new();
+    for length in size_length(payload.len())..=8 {
+        let candidate = encode_element(INFO, &payload, length);
+        match room.checked_sub(candidate.len() as u64) {
+            Some(0) | Some(2..) => {
+                element = candidate;
+                break;
+            }
+            _ => {}
+        }
+    }
+    if element.is_empty() {
+        return Err(no_room());
+    }
+    let rest = room as usize - element.len();
+    if rest > 0 {
+        let length = if rest <= 128 { 1 } else { 8 };
+        element.extend(encode_element(VOID, &vec![0; rest - 1 - length], length));
+    }
+    Ok(HeaderEdit {
+        offset: info.start,
+        replaced: room,
+        bytes: element,
+        len: None,
+    })
+}
+
+fn encode_box(kind: &[u8; 4], payload: &[u8]) -> Result<Vec<u8>, String> {
+    let size =
+        u32::try_from(payload.len() + 8).map_err(|_| "An MP4 box is too large.".to_string())?;
+    let mut bytes = size.to_be_bytes().to_vec();
+    bytes.extend_from_slice(kind);
+    bytes.extend_from_slice(payload);
+    Ok(bytes)
+}
+
+/// Re-encodes the child boxes in `data`, replacing the first `kind` box with
+/// the payload returned by `update`, or adding one at the end.
+fn with_child(
+    data: &[u8],
+    kind: &[u8; 4],
+    update: impl FnOnce(Option<&[u8]>) -> Result<Vec<u8>, String>,
+) -> Result<Vec<u8>, String> {
+    let mut update = Some(update);
+    let mut bytes = Vec::new();
+    for (child, payload) in all_mp4_boxes(data).ok_or_else(malformed)? {
+        if &child == kind {
+            if let Some(update) = update.take() {
+                bytes.extend(encode_box(kind, &update(Some(payload))?)?);
+                continue;
+            }
+        }
+        bytes.extend(encode_box(&child, payload)?);
+    }
+    if let Some(update) = update {
+        bytes.extend(encode_box(kind, &update(None)?)?);
+    }
+    Ok(bytes)
+}
+
+/// Sets the title in `moov/udta/meta/ilst`, creating the boxes as needed.
+fn moov_with_title(moov: &[u8], title: &str) -> Result<Vec<u8>, String> {
+    with_child(moov, b"udta", |udta| {
+        with_child(udta.unwrap_or_default(), b"meta", |meta| {
+            let (version, children) = match meta {
+                Some(meta) => meta_children(meta),
+                None => (&[0u8; 4][..], &[][..]),
+            };
+            let mut children = children.to_vec();
+            if meta.is_none() {
+                // Handler of iTunes style metadata.
+                let mut hdlr = vec![0u8; 8];
+                hdlr.extend_from_slice(b"mdirappl");
+                hdlr.extend_from_slice(&[0u8; 9]);
+                children = encode_box(b"hdlr", &hdlr)?;
+            }
+            let mut meta = version.to_vec();
+            meta.extend(with_child(&children, b"ilst", |ilst| {
+                with_child(ilst.unwrap_or_default(), TITLE_ITEM, |_| {
+                    // Type 1 is UTF-8 text, followed by an empty locale.
+                    let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+                    data.extend_from_slice(title.as_bytes());
+                    encode_box(b"data", &data)
+                })
+            })?);
+            Ok(meta)
+        })
+    })
+}
+
+/// Rewrites the movie box with the new title. If other boxes follow, it has
+/// to fit into the space of the old box and any free box after it, since
+/// moving the media data would break the sample offsets.
+fn mp4_title_edit<R: Read + Seek>(file: &mut R, title: &str) -> Result<HeaderEdit, String> {
+    let end = file.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+    let boxes = top_level_boxes(file)?;
+    let index = boxes
+        .iter()
+        .position(|b| &b.kind == b"moov")
+        .ok_or_else(|| "The MP4 file contains no movie header.".to_string())?;
+    let moov = &boxes[index];
+    file.seek(SeekFrom::Start(moov.start + moov.header_size))
+        .map_err(|e| e.to_string())?;
+    let payload = read_payload(file, Some(moov.size - moov.header_size))?;
+    let mut element = encode_box(b"moov", &moov_with_title(&payload, title)?)?;
+
+    let is_last = moov.size >= end - moov.start;
+    let mut room = moov.size.min(end - moov.start);
+    if !is_last {
+        if let Some(free) = boxes.get(index + 1) {
+            let is_free = &free.kind == b"free" || &free.kind == b"skip";
+            if is_free && free.size <= end - free.start {
+                room += free.size;
+            }
+        }
+        match room.checked_sub(element.len() as u64) {
+            Some(0) => {}
+            Some(rest @ 8..) => element.extend(encode_box(b"free", &vec![0; rest as usize - 8])?),
+            _ => return Err(no_room()),
+        }
+    }
+    Ok(HeaderEdit {
+        offset: moov.start,
+        replaced: room,
+        len: is_last.then_some(moov.start + element.len() as u64),
+        bytes: element,
+    })
+}
+
+// --- Plausibility ---
+
+/// How the length of a file compares to a typical episode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DurationCheck {
+    Plausible,
+    TooShort,
+    TooLong,
+    /// About twice as long, likely two episodes in one file.
+    DoubleEpisode,
+}
+
+pub fn check_duration(duration: Duration, expected: Duration) -> DurationCheck {
+    let ratio = duration.as_secs_f64() / expected.as_secs_f64().max(1.0);
+    match ratio {
+        r if r < 0.6 => DurationCheck::TooShort,
+        r if r <= 1.45 => DurationCheck::Plausible,
+        r if (1.7..=2.3).contains(&r) => DurationCheck::DoubleEpisode,
+        _ => DurationCheck::TooLong,
+    }
+}
+
+/// Typical episode length: the runtime from the provider, otherwise the
+/// median length of the probed files if there are at least three.
+pub fn expected_duration(runtime_minutes: Option<u32>, files: &[LocalFile]) -> Option<Duration> {
+    if let Some(minutes) = runtime_minutes.filter(|minutes| *minutes > 0) {
+        return Some(Duration::from_secs(minutes as u64 * 60));
+    }
+    let mut durations: Vec<Duration> = files
+        .iter()
+        .filter_map(|file| file.media.as_ref()?.duration)
+        .collect();
+    if durations.len() < 3 {
+        return None;
+    }
+    durations.sort();
+    Some(durations[durations.len() / 2])
+}
+
+/// Explains why a file's length does not fit the episode length, if it
+/// does not.
+pub fn duration_warning(duration: Duration, expected: Duration) -> Option<String> {
+    let (minutes, expected_minutes) = (
+        (duration.as_secs() + 30) / 60,
+        (expected.as_secs() + 30) / 60,
+    );
+    match check_duration(duration, expected) {
+        DurationCheck::Plausible => None,
+        DurationCheck::DoubleEpisode => Some(format!(
+            "{} min, twice the usual {} min: this may be a double episode.",
+            minutes, expected_minutes
+        )),
+        DurationCheck::TooLong | DurationCheck::TooShort => Some(format!(
+            "{} min, but episodes run about {} min.",
+            minutes, expected_minutes
+        )),
+    }
+}
@@ -0,0 +1,62 @@
+// src/app/profile.rs
+use serde::{Deserialize, Serialize};
+
+use super::omdb;
+use super::sanitize::NamingOptions;
+use super::scan::imdb_id_from_link;
+use super::template;
+
+/// Everything needed to process another season of a series, saved under a
+/// name so it does not have to be typed again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SeriesProfile {
+    pub name: String,
+    pub imdb_link: String,
+    pub series_directory: String,
+    /// Season processed last.
+    pub season_number: u32,
+    /// Metadata server. If empty, the one from the settings is used.
+    pub base_url: String,
+    pub naming: NamingOptions,
+}
+
+impl Default for SeriesProfile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            imdb_link: String::new(),
+            series_directory: String::new(),
+            season_number: 1,
+            base_url: String::new(),
+            naming: NamingOptions::default(),
+        }
+    }
+}
+
+impl SeriesProfile {
+    /// Checks the profile for problems. `others` are the other saved
+    /// profiles, whose names must not be reused. Returns one message per
+    /// problem.
+    pub fn validate(&self, others: &[SeriesProfile]) -> Vec<String> {
+        let mut problems = Vec::new();
+        let name = self.name.trim();
+        if name.is_empty() {
+            problems.push("The profile needs a name.".to_string());
+        } else if others.iter().any(|other| other.name.trim() == name) {
+            problems.push(format!("A profile named '{}' already exists.", name));
+        }
+        if imdb_id_from_link(&self.imdb_link).is_none() {
+            problems.push("The IMDb link contains no IMDb ID.".to_string());
+        }
+        if !self.base_url.trim().is_empty() {
+            if let Err(e) = omdb::validate_base_url(self.base_url.trim()) {
+                problems.push(e);
+            }
+        }
+        if let Err(e) = template::validate(&self.naming.template) {
+            problems.push(e);
+        }
+        problems
+    }
+}
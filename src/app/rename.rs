@@ -0,0 +1,488 @@
+// src/app/rename.rs
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// A single file rename that is part of a rename plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameOperation {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum RenameStatus {
+    Renamed,
+    Failed,
+}
+
+/// The result of renaming one file, as shown in the report window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RenameOutcome {
+    pub status: RenameStatus,
+    pub old_path: PathBuf,
+    /// The target path, if one could be computed for the file.
+    pub new_path: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+impl RenameOutcome {
+    pub fn renamed(operation: &RenameOperation) -> Self {
+        Self {
+            status: RenameStatus::Renamed,
+            old_path: operation.from.clone(),
+            new_path: Some(operation.to.clone()),
+            error: None,
+        }
+    }
+
+    pub fn failed(old_path: PathBuf, new_path: Option<PathBuf>, error: impl Into<String>) -> Self {
+        Self {
+            status: RenameStatus::Failed,
+            old_path,
+            new_path,
+            error: Some(error.into()),
+        }
+    }
+
+    /// Returns the operation to run again if this outcome is retried.
+    pub fn retry_operation(&self) -> Option<RenameOperation> {
+        match (&self.status, &self.new_path) {
+            (RenameStatus::Failed, Some(new_path)) => Some(RenameOperation {
+                from: self.old_path.clone(),
+                to: new_path.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Renames every file and records the outcome of each operation.
+/// A failing rename does not stop the remaining ones.
+///
+/// Operations are reordered so that a file is only renamed once its target
+/// name has been vacated, and cycles (E01 <-> E02) are broken by moving one
+/// file of each cycle to a temporary name first. Existing files are never
+/// overwritten.
+pub fn apply_renames(operations: &[RenameOperation]) -> Vec<RenameOutcome> {
+    let schedule = schedule_operations(operations);
+    let mut errors: Vec<Option<String>> = vec![None; operations.len()];
+
+    for step in &schedule.steps {
+        if errors[step.operation].is_some() {
+            continue;
+        }
+        let result = if step.from == step.to {
+            Ok(())
+        } else if step.to.exists() && !is_same_file(&step.from, &step.to) {
+            Err("Target file already exists".to_string())
+        } else {
+            std::fs::rename(&step.from, &step.to).map_err(|e| e.to_string())
+        };
+        if let Err(e) = result {
+            errors[step.operation] = Some(if step.from != operations[step.operation].from {
+                format!("{} (file left at '{}')", e, step.from.display())
+            } else {
+                e
+            });
+        }
+    }
+
+    operations
+        .iter()
+        .zip(errors)
+        .map(|(operation, error)| match error {
+            None => RenameOutcome::renamed(operation),
+            Some(e) => RenameOutcome::failed(operation.from.clone(), Some(operation.to.clone()), e),
+        })
+        .collect()
+}
+
+/// A single filesystem rename performed on behalf of an operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameStep {
+    /// Index of the operation in the plan this step belongs to.
+    pub operation: usize,
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// The order in which the steps of a plan are executed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenameSchedule {
+    pub steps: Vec<RenameStep>,
+    /// Operations forming rename cycles, each resolved through a temporary name.
+    pub cycles: Vec<Vec<usize>>,
+}
+
+/// Finds the cycles in a plan, e.g. `A -> B` together with `B -> A`.
+/// Each cycle is returned as the indices of its operations in rename order.
+pub fn find_cycles(operations: &[RenameOperation]) -> Vec<Vec<usize>> {
+    let successors = successors(operations);
+    let mut state = vec![0u8; operations.len()]; // 0 = unvisited, 1 = on path, 2 = done
+    let mut cycles = Vec::new();
+
+    for start in 0..operations.len() {
+        let mut path = Vec::new();
+        let mut current = Some(start);
+        while let Some(index) = current {
+            match state[index] {
+                0 => {
+                    state[index] = 1;
+                    path.push(index);
+                    current = successors[index];
+                }
+                1 => {
+                    // We came back to a node on the current path: everything
+                    // from its first occurrence forms a cycle.
+                    let position = path.iter().position(|&i| i == index).unwrap_or(0);
+                    let cycle: Vec<usize> = path[position..].to_vec();
+                    // Renaming a file to its own name is not a cycle worth reporting.
+                    if cycle.len() > 1 {
+                        cycles.push(cycle);
+                    }
+                    break;
+                }
+                _ => break,
+            }
+        }
+        for index in path {
+            state[index] = 2;
+        }
+    }
+    cycles
+}
+
+/// Orders the operations so that no rename targets a name that is still in
+/// use by another file of the plan.
+pub fn schedule_operations(operations: &[RenameOperation]) -> RenameSchedule {
+    let cycles = find_cycles(operations);
+    let mut sources: Vec<PathBuf> = operations.iter().map(|o| o.from.clone()).collect();
+    let mut steps = Vec::new();
+
+    // Break every cycle by moving its first file out of the way.
+    for (cycle_index, cycle) in cycles.iter().enumerate() {
+        let first = cycle[0];
+        let temporary = temporary_path(&operations[first].from, cycle_index);
+        steps.push(RenameStep {
+            operation: first,
+            from: operations[first].from.clone(),
+            to: temporary.clone(),
+        });
+        sources[first] = temporary;
+    }
+
+    // Operation `i` has to wait for the operation currently occupying its target.
+    let blocker: Vec<Option<usize>> = operations
+        .iter()
+        .enumerate()
+        .map(|(i, operation)| {
+            sources
+                .iter()
+                .enumerate()
+                .position(|(j, source)| j != i && *source == operation.to)
+        })
+        .collect();
+
+    let mut emitted = vec![false; operations.len()];
+    for start in 0..operations.len() {
+        // Walk the chain of blockers and emit it from the end.
+        let mut chain = Vec::new();
+        let mut current = Some(start);
+        while let Some(index) = current {
+            if emitted[index] || chain.contains(&index) {
+                break;
+            }
+            chain.push(index);
+            current = blocker[index];
+        }
+        for index in chain.into_iter().rev() {
+            emitted[index] = true;
+            steps.push(RenameStep {
+                operation: index,
+                from: sources[index].clone(),
+                to: operations[index].to.clone(),
+            });
+        }
+    }
+
+    RenameSchedule { steps, cycles }
+}
+
+/// For every operation, the operation whose source is its target.
+fn successors(operations: &[RenameOperation]) -> Vec<Option<usize>> {
+    let by_source: HashMap<&PathBuf, usize> = operations
+        .iter()
+        .enumerate()
+        .map(|(i, o)| (&o.from, i))
+        .collect();
+    operations
+        .iter()
+        .map(|o| by_source.get(&o.to).copied())
+        .collect()
+}
+
+/// Reverts the successful renames of a previous run by moving every renamed
+/// file back to its old name.
+pub fn undo_renames(outcomes: &[RenameOutcome]) -> Vec<RenameOutcome> {
+    let operations: Vec<RenameOperation> = outcomes
+        .iter()
+        .filter(|outcome| outcome.status == RenameStatus::Renamed)
+        .filter_map(|outcome| {
+            Some(RenameOperation {
+                from: outcome.new_path.clone()?,
+                to: outcome.old_path.clone(),
+            })
+        })
+        .collect();
+    apply_renames(&operations)
+}
+
+/// Renames the files as a single transaction: either every operation is
+/// applied or, if any step fails, the completed steps are rolled back.
+///
+/// Files are first moved to temporary names and then to their targets, so
+/// plans that swap names between files (E01 <-> E02) do not collide.
+pub fn apply_renames_transactional(operations: &[RenameOperation]) -> Vec<RenameOutcome> {
+    let staged: Vec<RenameOperation> = operations
+        .iter()
+        .enumerate()
+        .map(|(index, operation)| RenameOperation {
+            from: operation.from.clone(),
+            to: temporary_path(&operation.from, index),
+        })
+        .collect();
+    if let Err((index, error)) = check_operations(operations, &staged) {
+        return abort_outcomes(operations, index, &error, "Not renamed");
+    }
+
+    let mut journal = RenameJournal::default();
+    let phases = [
+        staged.clone(),
+        staged
+            .iter()
+            .zip(operations)
+            .map(|(stage, operation)| RenameOperation {
+                from: stage.to.clone(),
+                to: operation.to.clone(),
+            })
+            .collect::<Vec<_>>(),
+    ];
+    for phase in &phases {
+        for (index, step) in phase.iter().enumerate() {
+            if let Err(e) = journal.rename(step) {
+                let rollback_errors = journal.undo();
+                let state = if rollback_errors.is_empty() {
+                    "Rolled back".to_string()
+                } else {
+                    format!("Rollback incomplete: {}", rollback_errors.join("; "))
+                };
+                return abort_outcomes(operations, index, &e.to_string(), &state);
+            }
+        }
+    }
+
+    operations.iter().map(RenameOutcome::renamed).collect()
+}
+
+/// Applies the operations of a plan together with the `failures`, files for
+/// which no target path could be computed. With `all_or_nothing` the plan is
+/// one transaction and any failure leaves every file untouched.
+pub fn apply_plan(
+    operations: &[RenameOperation],
+    mut failures: Vec<RenameOutcome>,
+    all_or_nothing: bool,
+) -> Vec<RenameOutcome> {
+    if all_or_nothing && failures.is_empty() {
+        return apply_renames_transactional(operations);
+    }
+    if all_or_nothing {
+        failures.extend(operations.iter().map(|operation| {
+            RenameOutcome::failed(
+                operation.from.clone(),
+                Some(operation.to.clone()),
+                "Not renamed because another rename failed",
+            )
+        }));
+    } else {
+        failures.extend(apply_renames(operations));
+    }
+    failures
+}
+
+/// Records completed renames so that they can be reverted in reverse order.
+#[derive(Debug, Default, Clone)]
+pub struct RenameJournal {
+    steps: Vec<RenameOperation>,
+}
+
+impl RenameJournal {
+    /// Renames a file and records the step on success.
+    pub fn rename(&mut self, operation: &RenameOperation) -> std::io::Result<()> {
+        std::fs::rename(&operation.from, &operation.to)?;
+        self.steps.push(operation.clone());
+        Ok(())
+    }
+
+    /// Reverts all recorded steps, newest first. Returns the steps that could
+    /// not be reverted.
+    pub fn undo(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        while let Some(step) = self.steps.pop() {
+            if let Err(e) = std::fs::rename(&step.to, &step.from) {
+                errors.push(format!(
+                    "could not move '{}' back to '{}': {}",
+                    step.to.display(),
+                    step.from.display(),
+                    e
+                ));
+            }
+        }
+        errors
+    }
+}
+
+/// Verifies that the plan can be applied without overwriting anything,
+/// neither at the targets nor at the temporary names of the `staged` moves.
+/// Returns the index of the offending operation and a description.
+fn check_operations(
+    operations: &[RenameOperation],
+    staged: &[RenameOperation],
+) -> Result<(), (usize, String)> {
+    let sources: std::collections::HashSet<&PathBuf> = operations.iter().map(|o| &o.from).collect();
+    let mut targets = std::collections::HashSet::new();
+    for (index, operation) in operations.iter().enumerate() {
+        if !operation.from.exists() {
+            return Err((index, "Source file does not exist".to_string()));
+        }
+        if !targets.insert(&operation.to) {
+            return Err((
+                index,
+                "Another file is renamed to the same name".to_string(),
+            ));
+        }
+        if operation.to.exists()
+            && !sources.contains(&operation.to)
+            && !is_same_file(&operation.from, &operation.to)
+        {
+            return Err((index, "Target file already exists".to_string()));
+        }
+    }
+    for (index, stage) in staged.iter().enumerate() {
+        if is_taken(&stage.to) {
+            return Err((index, "Temporary name is already in use".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Whether both paths name the same file under names that differ only in
+/// case, e.g. `pilot.mkv` and `Pilot.mkv` on a case-insensitive file system.
+/// Such a target "exists" only because it is the source itself.
+fn is_same_file(a: &std::path::Path, b: &std::path::Path) -> bool {
+    if a.to_string_lossy().to_lowercase() != b.to_string_lossy().to_lowercase() {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (std::fs::metadata(a), std::fs::metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Builds the outcomes of an aborted transaction: the operation at `index`
+/// carries the error, all others are reported with `state`.
+fn abort_outcomes(
+    operations: &[RenameOperation],
+    index: usize,
+    error: &str,
+    state: &str,
+) -> Vec<RenameOutcome> {
+    operations
+        .iter()
+        .enumerate()
+        .map(|(i, operation)| {
+            let message = if i == index {
+                format!("{} ({})", error, state)
+            } else {
+                format!("{} because another rename failed", state)
+            };
+            RenameOutcome::failed(operation.from.clone(), Some(operation.to.clone()), message)
+        })
+        .collect()
+}
+
+/// A temporary name next to the original file that is not in use yet. A
+/// counter is appended if a file left behind by an earlier run, e.g. one that
+/// crashed with the same process ID, already has the name.
+fn temporary_path(path: &std::path::Path, index: usize) -> PathBuf {
+    let name = |suffix: String| {
+        path.with_file_name(format!(
+            ".series_renamer_{}_{}{}.tmp",
+            std::process::id(),
+            index,
+            suffix
+        ))
+    };
+    let mut candidate = name(String::new());
+    let mut attempt = 1;
+    while is_taken(&candidate) {
+        candidate = name(format!("_{}", attempt));
+        attempt += 1;
+    }
+    candidate
+}
+
+/// Whether a file, directory or (possibly dangling) link has the name.
+fn is_taken(path: &std::path::Path) -> bool {
+    std::fs::symlink_metadata(path).is_ok()
+}
+
+/// Formats the outcomes as CSV with a header row.
+pub fn outcomes_to_csv(outcomes: &[RenameOutcome]) -> String {
+    let mut csv = String::from("status,old_path,new_path,error\n");
+    for outcome in outcomes {
+        let status = match outcome.status {
+            RenameStatus::Renamed => "Renamed",
+            RenameStatus::Failed => "Failed",
+        };
+        let new_path = outcome
+            .new_path
+            .as_ref()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let fields = [
+            status.to_string(),
+            outcome.old_path.to_string_lossy().to_string(),
+            new_path,
+            outcome.error.clone().unwrap_or_default(),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&line.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Formats the outcomes as a pretty-printed JSON array.
+pub fn outcomes_to_json(outcomes: &[RenameOutcome]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(outcomes)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,230 @@
+// src/app/sanitize.rs
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use unicode_normalization::UnicodeNormalization;
+
+use super::template::DEFAULT_TEMPLATE;
+
+/// Names that Windows reserves for devices, with or without an extension.
+/// Windows also treats the superscript digits as port numbers.
+const WINDOWS_RESERVED_NAMES: [&str; 32] = [
+    "CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$", "COM0", "COM1", "COM2", "COM3", "COM4",
+    "COM5", "COM6", "COM7", "COM8", "COM9", "COM¹", "COM²", "COM³", "LPT0", "LPT1", "LPT2", "LPT3",
+    "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9", "LPT¹", "LPT²", "LPT³",
+];
+
+/// Maximum length of a single file name on common filesystems.
+const MAX_NAME_LENGTH: usize = 255;
+/// Classic Windows `MAX_PATH` limit, excluding the terminating NUL.
+const WINDOWS_MAX_PATH: usize = 259;
+
+/// Unicode normalization form applied to the title placeholder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum UnicodeForm {
+    /// Keep the title as the provider returned it.
+    Unchanged,
+    /// Composed form, expected by Windows and most Linux tools.
+    #[default]
+    Nfc,
+    /// Decomposed form, as used by macOS HFS+ and some NAS shares.
+    Nfd,
+}
+
+impl UnicodeForm {
+    pub const ALL: [UnicodeForm; 3] = [UnicodeForm::Unchanged, UnicodeForm::Nfc, UnicodeForm::Nfd];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            UnicodeForm::Unchanged => "Unchanged",
+            UnicodeForm::Nfc => "NFC (composed)",
+            UnicodeForm::Nfd => "NFD (decomposed)",
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            UnicodeForm::Unchanged => text.to_string(),
+            UnicodeForm::Nfc => text.nfc().collect(),
+            UnicodeForm::Nfd => text.nfd().collect(),
+        }
+    }
+}
+
+/// Everything that controls how a title ends up in a file name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NamingOptions {
+    pub policy: SanitizePolicy,
+    pub unicode_form: UnicodeForm,
+    /// File name without extension, see [`super::template`].
+    pub template: String,
+}
+
+impl Default for NamingOptions {
+    fn default() -> Self {
+        Self {
+            policy: SanitizePolicy::default(),
+            unicode_form: UnicodeForm::default(),
+            template: DEFAULT_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl NamingOptions {
+    /// Turns a title from the provider into the title placeholder of a name.
+    pub fn title(&self, title: &str) -> String {
+        // Sanitizing can only remove characters, so normalizing afterwards
+        // keeps combining sequences intact.
+        self.unicode_form.apply(&self.policy.sanitize(title))
+    }
+}
+
+/// Rules for turning episode titles into valid file names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SanitizePolicy {
+    /// Replaces characters Windows rejects and avoids reserved names.
+    #[default]
+    WindowsSafe,
+    /// Only replaces the path separator and NUL.
+    PosixMinimal,
+    /// Windows rules plus limits of NAS shares that store names as UTF-8.
+    SmbSafe,
+    /// Transliterates the title to ASCII, then applies the Windows rules.
+    AsciiTransliteration,
+}
+
+impl SanitizePolicy {
+    pub const ALL: [SanitizePolicy; 4] = [
+        SanitizePolicy::WindowsSafe,
+        SanitizePolicy::PosixMinimal,
+        SanitizePolicy::SmbSafe,
+        SanitizePolicy::AsciiTransliteration,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SanitizePolicy::WindowsSafe => "Windows-safe",
+            SanitizePolicy::PosixMinimal => "POSIX minimal",
+            SanitizePolicy::SmbSafe => "SMB/NTFS-safe",
+            SanitizePolicy::AsciiTransliteration => "ASCII transliteration",
+        }
+    }
+
+    /// Replaces the characters of a title, or of a whole rendered name, that
+    /// are invalid in a file name.
+    /// Everything else, including punctuation like `'`, `,` and `&`, is kept.
+    pub fn sanitize(&self, title: &str) -> String {
+        let title = match self {
+            SanitizePolicy::AsciiTransliteration => deunicode::deunicode(title),
+            _ => title.to_string(),
+        };
+
+        let mut sanitized = String::with_capacity(title.len());
+        for c in title.chars() {
+            match self {
+                SanitizePolicy::PosixMinimal => match c {
+                    '/' => sanitized.push('-'),
+                    '\0' => {}
+                    c => sanitized.push(c),
+                },
+                _ => match c {
+                    ':' => sanitized.push_str(" -"),
+                    '/' | '\\' | '|' => sanitized.push('-'),
+                    '"' => sanitized.push('\''),
+                    '<' | '>' | '?' | '*' => {}
+                    c if c.is_control() => {}
+                    // Characters outside the BMP (e.g. emoji) are mangled by many SMB servers.
+                    c if *self == SanitizePolicy::SmbSafe && (c as u32) > 0xFFFF => {}
+                    c => sanitized.push(c),
+                },
+            }
+        }
+
+        // Collapse the whitespace left behind by removed characters.
+        sanitized.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Applies the name-level rules of the policy to a complete file name:
+    /// reserved names and trailing dots and spaces. Fails if the stem ends up
+    /// empty or longer than [`Self::max_stem_length`] allows, rather than
+    /// cutting off a part of the name the template put there.
+    pub fn finalize_file_name(
+        &self,
+        stem: &str,
+        extension: &str,
+        parent: Option<&Path>,
+    ) -> Result<String, String> {
+        let mut stem = stem.to_string();
+
+        if *self != SanitizePolicy::PosixMinimal {
+            stem = stem.trim_end_matches(['.', ' ']).to_string();
+            let base = stem.split('.').next().unwrap_or("").trim();
+            if WINDOWS_RESERVED_NAMES
+                .iter()
+                .any(|reserved| reserved.eq_ignore_ascii_case(base))
+            {
+                stem.push('_');
+            }
+        }
+
+        if stem.trim().is_empty() {
+            return Err("the new name would be empty".to_string());
+        }
+        if self.length_of(&stem) > self.max_stem_length(extension, parent) {
+            return Err("the new path would be too long".to_string());
+        }
+        if extension.is_empty() {
+            Ok(stem)
+        } else {
+            Ok(format!("{}.{}", stem, extension))
+        }
+    }
+
+    /// How long the stem of a name in `parent` may be. Every filesystem limits
+    /// the name itself; on Windows and SMB shares the whole path also has to
+    /// fit into `MAX_PATH`.
+    pub fn max_stem_length(&self, extension: &str, parent: Option<&Path>) -> usize {
+        let suffix = if extension.is_empty() {
+            0
+        } else {
+            1 + self.length_of(extension)
+        };
+        let mut budget = MAX_NAME_LENGTH.saturating_sub(suffix);
+        if cfg!(windows) || *self == SanitizePolicy::SmbSafe {
+            if let Some(parent) = parent {
+                // The parent path plus a separator count against MAX_PATH.
+                let used = self.length_of(&parent.to_string_lossy()) + 1 + suffix;
+                budget = budget.min(WINDOWS_MAX_PATH.saturating_sub(used));
+            }
+        }
+        budget
+    }
+
+    /// Length of a name as the target filesystem counts it.
+    pub fn length_of(&self, name: &str) -> usize {
+        match self {
+            // NTFS limits names to 255 UTF-16 code units.
+            SanitizePolicy::WindowsSafe | SanitizePolicy::AsciiTransliteration => {
+                name.encode_utf16().count()
+            }
+            // Most Unix filesystems (and NAS shares backed by them) count bytes.
+            SanitizePolicy::PosixMinimal | SanitizePolicy::SmbSafe => name.len(),
+        }
+    }
+
+    /// Cuts a name down to `max` units without splitting a character.
+    pub fn truncate(&self, name: &str, max: usize) -> String {
+        let mut result = String::new();
+        for c in name.chars() {
+            let mut buffer = [0u8; 4];
+            if self.length_of(&result) + self.length_of(c.encode_utf8(&mut buffer)) > max {
+                break;
+            }
+            result.push(c);
+        }
+        if *self != SanitizePolicy::PosixMinimal {
+            result = result.trim_end_matches(['.', ' ']).to_string();
+        }
+        result
+    }
+}
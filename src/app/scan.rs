@@ -0,0 +1,48 @@
+// src/app/scan.rs
+use std::path::Path;
+use walkdir::WalkDir;
+
+use super::parse::is_video_file;
+use super::probe;
+use super::sorting::natural_cmp;
+use super::LocalFile;
+
+/// Lists all files below `directory`, sorted naturally by path. Video files
+/// are probed for their duration, resolution and codecs.
+pub fn scan_directory(directory: impl AsRef<Path>) -> Vec<LocalFile> {
+    scan_directory_except(directory, None)
+}
+
+/// Like [`scan_directory`], but leaves out the files below `excluded`, such
+/// as the folder extra copies of episodes were moved to.
+pub fn scan_directory_except(
+    directory: impl AsRef<Path>,
+    excluded: Option<&Path>,
+) -> Vec<LocalFile> {
+    let mut files: Vec<LocalFile> = WalkDir::new(directory)
+        .into_iter()
+        .filter_entry(|e| Some(e.path()) != excluded)
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| {
+            let path = e.into_path();
+            let media = if is_video_file(&path) {
+                probe::probe_file(&path)
+                    .map_err(|error| log::debug!("Could not probe '{}': {}", path.display(), error))
+                    .ok()
+            } else {
+                None
+            };
+            LocalFile { path, media }
+        })
+        .collect();
+    files.sort_by(|a, b| natural_cmp(&a.path.to_string_lossy(), &b.path.to_string_lossy()));
+    files
+}
+
+/// Extracts the IMDb ID (e.g. `tt0903747`) from an IMDb link.
+pub fn imdb_id_from_link(link: &str) -> Option<String> {
+    link.split(['/', '?', '&', '='])
+        .find(|s| s.starts_with("tt") && s.len() > 2 && s[2..].chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+}
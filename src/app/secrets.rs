@@ -0,0 +1,142 @@
+// src/app/secrets.rs
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::settings::AppSettings;
+
+/// Service name the API key is stored under in the OS keyring.
+const KEYRING_SERVICE: &str = "series_renamer";
+/// Account name the API key is stored under in the OS keyring.
+const KEYRING_USER: &str = "omdb_api_key";
+
+/// A place where the OMDb API key is kept outside of the settings file.
+pub trait SecretStore: Send {
+    /// Returns the stored key, or `None` if no key has been stored yet.
+    fn load(&self) -> Result<Option<String>, String>;
+    fn store(&self, secret: &str) -> Result<(), String>;
+    fn delete(&self) -> Result<(), String>;
+    /// Human readable name shown in the settings window.
+    fn description(&self) -> String;
+}
+
+/// Stores the key in the Secret Service on Linux, the Keychain on macOS and
+/// the Credential Manager on Windows.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct KeyringStore {
+    entry: keyring::Entry,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl KeyringStore {
+    pub fn new() -> Result<Self, String> {
+        let entry =
+            keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).map_err(|e| e.to_string())?;
+        Ok(Self { entry })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SecretStore for KeyringStore {
+    fn load(&self) -> Result<Option<String>, String> {
+        match self.entry.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn store(&self, secret: &str) -> Result<(), String> {
+        self.entry.set_password(secret).map_err(|e| e.to_string())
+    }
+
+    fn delete(&self) -> Result<(), String> {
+        match self.entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn description(&self) -> String {
+        "OS keyring".to_string()
+    }
+}
+
+/// Stores the key in a plain file readable only by the current user. Used
+/// when no keyring is available, and by tests.
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The fallback file next to the `confy` settings file.
+    pub fn next_to_settings() -> Result<Self, String> {
+        let settings_path = AppSettings::file_path().map_err(|e| e.to_string())?;
+        Ok(Self::new(settings_path.with_file_name("api_key")))
+    }
+}
+
+impl SecretStore for FileStore {
+    fn load(&self) -> Result<Option<String>, String> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(secret) => Ok(Some(secret.trim().to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Writes the key to a new file that is only readable by the current user
+    /// from the start, then replaces the old file with it.
+    fn store(&self, secret: &str) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let temporary = self.path.with_file_name(format!(".{}.tmp", name));
+        let _ = std::fs::remove_file(&temporary);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let result = options.open(&temporary).and_then(|mut file| {
+            file.write_all(secret.as_bytes())?;
+            file.sync_all()?;
+            std::fs::rename(&temporary, &self.path)
+        });
+        result.map_err(|e| {
+            let _ = std::fs::remove_file(&temporary);
+            e.to_string()
+        })
+    }
+
+    fn delete(&self) -> Result<(), String> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn description(&self) -> String {
+        format!("file {}", self.path.display())
+    }
+}
+
+/// Opens the OS keyring, falling back to the local file if the keyring is
+/// unavailable (e.g. no Secret Service daemon is running).
+pub fn default_store() -> Result<Box<dyn SecretStore>, String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(store) = KeyringStore::new() {
+        // Probe the keyring once, so a missing daemon is detected up front.
+        if store.load().is_ok() {
+            return Ok(Box::new(store));
+        }
+    }
+    Ok(Box::new(FileStore::next_to_settings()?))
+}
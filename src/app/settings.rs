@@ -1,16 +1,147 @@
 // src/settings.rs
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
-#[derive(Debug, Serialize, Deserialize)]
+use super::duplicates::{self, KeepRule};
+use super::omdb::{self, RetryPolicy};
+use super::sanitize::NamingOptions;
+use super::scan::imdb_id_from_link;
+use super::template;
+use super::watch::WatchFolder;
+
+/// Name under which `confy` stores the settings file.
+pub const APP_NAME: &str = "series_renamer";
+
+/// Value older versions wrote to the settings file before a key was entered.
+pub const PLACEHOLDER_API_KEY: &str = "YOUR_API_KEY_HERE";
+
+/// Settings stored in the `confy` config file, shared by the window and the
+/// `--watch` daemon.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
+    /// Plain text key written by older versions. It is moved into the secret
+    /// store on startup and no longer written back.
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub api_key: String,
+    /// Roll back the whole plan if any rename fails.
+    pub all_or_nothing: bool,
+    /// Store the episode title in the title tag of renamed MKV and MP4
+    /// files, so players show it.
+    pub write_title_tags: bool,
+    /// Where extra copies of an episode are moved. Relative folders are
+    /// inside the series directory.
+    pub duplicates_folder: String,
+    /// Which copy of an episode is kept.
+    pub keep_rule: KeepRule,
+    /// How episode titles are turned into file names.
+    pub naming: NamingOptions,
+    /// Server metadata requests are sent to. Can point at a caching proxy
+    /// or a local stub server.
+    pub base_url: String,
+    /// Seconds to wait for a metadata response before giving up.
+    pub request_timeout_secs: u64,
+    /// How often a failed metadata request is retried.
+    pub max_retries: u32,
+    /// Delay before the first retry in milliseconds, doubled for each retry.
+    pub retry_backoff_ms: u64,
+    /// Directories the `--watch` daemon renames new episodes in.
+    pub watch_folders: Vec<WatchFolder>,
+    /// Seconds a new file's size must stay the same before it is handled.
+    pub watch_settle_secs: u64,
 }
 
 /// `confy` requires a default implementation.
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
-            api_key: String::from("YOUR_API_KEY_HERE"),
+            api_key: String::new(),
+            all_or_nothing: true,
+            write_title_tags: false,
+            duplicates_folder: duplicates::DEFAULT_FOLDER.to_string(),
+            keep_rule: KeepRule::default(),
+            naming: NamingOptions::default(),
+            base_url: omdb::DEFAULT_BASE_URL.to_string(),
+            request_timeout_secs: 15,
+            max_retries: 3,
+            retry_backoff_ms: 1000,
+            watch_folders: Vec::new(),
+            watch_settle_secs: 30,
+        }
+    }
+}
+
+impl AppSettings {
+    pub fn load() -> Result<Self, confy::ConfyError> {
+        confy::load(APP_NAME, None)
+    }
+
+    pub fn store(&self) -> Result<(), confy::ConfyError> {
+        confy::store(APP_NAME, None, self)
+    }
+
+    pub fn file_path() -> Result<PathBuf, confy::ConfyError> {
+        confy::get_configuration_file_path(APP_NAME, None)
+    }
+
+    /// Checks the values for problems. Returns one message per problem.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Err(e) = omdb::validate_base_url(&self.base_url) {
+            problems.push(e);
+        }
+        if !(1..=300).contains(&self.request_timeout_secs) {
+            problems.push("The request timeout must be between 1 and 300 seconds.".to_string());
+        }
+        if self.max_retries > 10 {
+            problems.push("At most 10 retries are allowed.".to_string());
+        }
+        if !(100..=60_000).contains(&self.retry_backoff_ms) {
+            problems.push("The retry delay must be between 100 and 60000 ms.".to_string());
+        }
+        if let Err(e) = template::validate(&self.naming.template) {
+            problems.push(e);
+        }
+        for folder in &self.watch_folders {
+            if folder.directory.trim().is_empty() {
+                problems.push("Every watch folder needs a directory.".to_string());
+            }
+            if !folder.imdb_link.trim().is_empty() && imdb_id_from_link(&folder.imdb_link).is_none()
+            {
+                problems.push(format!(
+                    "The IMDb link of the watch folder '{}' contains no IMDb ID.",
+                    folder.directory
+                ));
+            }
+        }
+        if !(1..=3600).contains(&self.watch_settle_secs) {
+            problems.push("The watch delay must be between 1 and 3600 seconds.".to_string());
+        }
+        problems
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            timeout: Duration::from_secs(self.request_timeout_secs),
+            max_retries: self.max_retries,
+            backoff: Duration::from_millis(self.retry_backoff_ms),
+        }
+    }
+
+    /// A client for the configured server using the given API key.
+    pub fn omdb_client(&self, api_key: &str) -> omdb::Client {
+        omdb::Client {
+            base_url: self.base_url.trim().to_string(),
+            api_key: api_key.to_string(),
+            policy: self.retry_policy(),
         }
     }
-}
\ No newline at end of file
+
+    /// Last modification time of the settings file, used to notice edits
+    /// made outside of the app.
+    pub fn modified_time() -> Option<SystemTime> {
+        let path = Self::file_path().ok()?;
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
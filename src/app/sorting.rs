@@ -0,0 +1,167 @@
+// src/app/sorting.rs
+use std::cmp::Ordering;
+
+/// Column a list in the assignment or confirmation view is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    Episode,
+    Title,
+    FileName,
+    NewName,
+}
+
+/// The current sort column and direction of a list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortState {
+    pub column: SortColumn,
+    pub ascending: bool,
+}
+
+impl SortState {
+    pub fn new(column: SortColumn) -> Self {
+        Self {
+            column,
+            ascending: true,
+        }
+    }
+
+    /// Applies the direction to an ordering computed in ascending order.
+    pub fn apply(&self, ordering: Ordering) -> Ordering {
+        if self.ascending {
+            ordering
+        } else {
+            ordering.reverse()
+        }
+    }
+
+    /// Shows a clickable column header. Clicking the active column flips the
+    /// direction, clicking another column sorts by it in ascending order.
+    pub fn header(&mut self, ui: &mut egui::Ui, label: &str, column: SortColumn) {
+        let is_active = self.column == column;
+        let text = match (is_active, self.ascending) {
+            (true, true) => format!("{} ⏶", label),
+            (true, false) => format!("{} ⏷", label),
+            (false, _) => label.to_string(),
+        };
+        if ui.selectable_label(is_active, text).clicked() {
+            if is_active {
+                self.ascending = !self.ascending;
+            } else {
+                *self = Self::new(column);
+            }
+        }
+    }
+}
+
+/// Compares two strings the way a human would, so that `2` sorts before `10`.
+/// Runs of digits are compared by their numeric value, everything else
+/// case-insensitively.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let a_digits = take_digits(&mut a_chars);
+                let b_digits = take_digits(&mut b_chars);
+                let a_number = a_digits.trim_start_matches('0');
+                let b_number = b_digits.trim_start_matches('0');
+                let ordering = a_number
+                    .len()
+                    .cmp(&b_number.len())
+                    .then_with(|| a_number.cmp(b_number));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+/// Compares two episode numbers numerically, falling back to a natural
+/// comparison for values that are not plain numbers.
+pub fn episode_number_cmp(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<u32>(), b.trim().parse::<u32>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => natural_cmp(a, b),
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(natural_cmp("ep2", "ep10"), Ordering::Less);
+        assert_eq!(natural_cmp("ep10", "ep2"), Ordering::Greater);
+        assert_eq!(natural_cmp("S01E09", "S01E10"), Ordering::Less);
+        assert_eq!(natural_cmp("track 9.mkv", "track 10.mkv"), Ordering::Less);
+        assert_eq!(natural_cmp("ep2", "ep2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn leading_zeros_only_break_ties() {
+        assert_eq!(natural_cmp("ep01", "ep2"), Ordering::Less);
+        assert_eq!(natural_cmp("ep002", "ep10"), Ordering::Less);
+        // Equal values still get a stable order instead of being equal.
+        assert_eq!(natural_cmp("01", "1"), Ordering::Less);
+        assert_eq!(natural_cmp("1", "01"), Ordering::Greater);
+        assert_eq!(natural_cmp("ep01a", "ep1b"), Ordering::Less);
+    }
+
+    #[test]
+    fn text_is_compared_ignoring_case() {
+        assert_eq!(natural_cmp("Ep2", "ep10"), Ordering::Less);
+        assert_eq!(natural_cmp("alpha", "Beta"), Ordering::Less);
+        assert_eq!(natural_cmp("ÄRGER", "ärger 2"), Ordering::Less);
+        // Names that differ only in case still have a fixed order.
+        assert_eq!(natural_cmp("ABC", "abc"), Ordering::Less);
+        assert_eq!(natural_cmp("", "a"), Ordering::Less);
+    }
+
+    #[test]
+    fn non_ascii_digits_are_text() {
+        // Arabic-Indic and full-width digits are not read as numbers.
+        assert_eq!(natural_cmp("ep١٠", "ep٢"), Ordering::Less);
+        assert_eq!(natural_cmp("ep２", "ep10"), Ordering::Greater);
+        assert_eq!(natural_cmp("ep٣", "ep٣"), Ordering::Equal);
+    }
+
+    #[test]
+    fn episode_numbers_sort_numerically_before_other_values() {
+        assert_eq!(episode_number_cmp("2", "10"), Ordering::Less);
+        assert_eq!(episode_number_cmp(" 3 ", "12"), Ordering::Less);
+        assert_eq!(episode_number_cmp("02", "2"), Ordering::Equal);
+        assert_eq!(episode_number_cmp("1", ""), Ordering::Less);
+        assert_eq!(episode_number_cmp("", "1"), Ordering::Greater);
+        assert_eq!(episode_number_cmp("Special", "99"), Ordering::Greater);
+        assert_eq!(episode_number_cmp("", ""), Ordering::Equal);
+        assert_eq!(episode_number_cmp("", "Special"), Ordering::Less);
+        assert_eq!(episode_number_cmp("1a", "1b"), Ordering::Less);
+        assert_eq!(episode_number_cmp("Part 2", "Part 10"), Ordering::Less);
+    }
+}
@@ -0,0 +1,191 @@
+// src/app/template.rs
+use super::parse::ReleaseInfo;
+
+/// Template used when none is configured, e.g. `S01E02 - Title`.
+pub const DEFAULT_TEMPLATE: &str = "S{season:02}E{episode:02} - {title}";
+
+/// Placeholders a template may contain. All but the first three are read
+/// from the original file name, see [`ReleaseInfo`].
+pub const PLACEHOLDERS: &[&str] = &[
+    "season",
+    "episode",
+    "title",
+    "quality",
+    "resolution",
+    "source",
+    "codec",
+    "hdr",
+    "audio",
+    "group",
+    "revision",
+];
+
+/// Values filled into the placeholders of a template.
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateValues<'a> {
+    pub season: u32,
+    /// Episode number as the provider returned it, usually a plain number.
+    pub episode: &'a str,
+    /// Title, already sanitized for file names.
+    pub title: &'a str,
+    /// Release details of the original file name. If `None`, their
+    /// placeholders are left empty.
+    pub release: Option<&'a ReleaseInfo>,
+}
+
+impl TemplateValues<'_> {
+    fn get(&self, name: &str) -> Option<String> {
+        let release =
+            |field: fn(&ReleaseInfo) -> String| Some(self.release.map(field).unwrap_or_default());
+        match name {
+            "season" => Some(self.season.to_string()),
+            "episode" => Some(self.episode.to_string()),
+            "title" => Some(self.title.to_string()),
+            "quality" => release(ReleaseInfo::quality),
+            "resolution" => release(|r| r.resolution.clone()),
+            "source" => release(|r| r.source.clone()),
+            "codec" => release(|r| r.video_codec.clone()),
+            "hdr" => release(|r| r.hdr.clone()),
+            "audio" => release(|r| r.audio.clone()),
+            "group" => release(|r| r.group.clone()),
+            "revision" => release(|r| r.revision.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Characters that only separate placeholders, like the ` - ` in front of
+/// `{group}`.
+fn is_separator(text: &str) -> bool {
+    text.chars().all(|c| matches!(c, ' ' | '.' | '-' | '_'))
+}
+
+/// A piece of a parsed template.
+enum Part<'a> {
+    Text(&'a str),
+    /// Placeholder name and the minimum width numbers are zero-padded to.
+    Placeholder(&'a str, usize),
+}
+
+fn parse(template: &str) -> Result<Vec<Part<'_>>, String> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find(['{', '}']) {
+        if rest[open..].starts_with('}') {
+            return Err("The template contains a '}' without a matching '{'.".to_string());
+        }
+        let Some(close) = rest[open..].find('}').map(|i| open + i) else {
+            return Err("The template contains a '{' without a matching '}'.".to_string());
+        };
+        if open > 0 {
+            parts.push(Part::Text(&rest[..open]));
+        }
+        let inner = &rest[open + 1..close];
+        let (name, width) = match inner.split_once(':') {
+            Some((name, width)) => {
+                let width = width.parse().ok().filter(|w| *w <= 9).ok_or_else(|| {
+                    format!(
+                        "'{{{}}}' has an invalid width, use e.g. '{{{}:02}}'.",
+                        inner, name
+                    )
+                })?;
+                (name, width)
+            }
+            None => (inner, 0),
+        };
+        parts.push(Part::Placeholder(name, width));
+        rest = &rest[close + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+    Ok(parts)
+}
+
+/// Checks a template for syntax errors, unknown placeholders and characters
+/// that would create subdirectories.
+pub fn validate(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("The name template must not be empty.".to_string());
+    }
+    if template.contains(['/', '\\']) {
+        return Err("The name template must not contain '/' or '\\'.".to_string());
+    }
+    let parts = parse(template)?;
+    let mut has_episode = false;
+    for part in &parts {
+        if let Part::Placeholder(name, _) = part {
+            if !PLACEHOLDERS.contains(name) {
+                return Err(format!(
+                    "Unknown placeholder '{{{}}}'. Available are {}.",
+                    name,
+                    PLACEHOLDERS
+                        .iter()
+                        .map(|p| format!("{{{}}}", p))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            has_episode |= *name == "episode";
+        }
+    }
+    if !has_episode {
+        return Err(
+            "The name template must contain '{episode}', otherwise all files get the same name."
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Fills in a template. Numbers are zero-padded to the width given after the
+/// colon, other values are inserted as they are. An empty value takes the
+/// brackets around it and the separator in front of it along, or the one
+/// after it if it comes first, so `{title} [{quality}]-{group}` also works
+/// for files without release details. Invalid templates are returned
+/// unchanged, they are rejected when the settings are saved.
+pub fn render(template: &str, values: &TemplateValues<'_>) -> String {
+    let Ok(parts) = parse(template) else {
+        return template.to_string();
+    };
+    // Rendered pieces, with whether each one is text from the template.
+    let mut pieces: Vec<(String, bool)> = parts
+        .iter()
+        .map(|part| match part {
+            Part::Text(text) => (text.to_string(), true),
+            Part::Placeholder(name, width) => match values.get(name) {
+                Some(value) if value.chars().all(|c| c.is_ascii_digit()) && !value.is_empty() => {
+                    (format!("{:0>width$}", value, width = *width), false)
+                }
+                Some(value) => (value, false),
+                None => (format!("{{{}}}", name), false),
+            },
+        })
+        .collect();
+
+    let empty: Vec<usize> = (0..pieces.len())
+        .filter(|&i| !pieces[i].1 && pieces[i].0.is_empty())
+        .collect();
+    for &i in &empty {
+        if i == 0 || i + 1 == pieces.len() {
+            continue;
+        }
+        for (open, close) in [('[', ']'), ('(', ')')] {
+            if pieces[i - 1].0.ends_with(open) && pieces[i + 1].0.starts_with(close) {
+                pieces[i - 1].0.pop();
+                pieces[i + 1].0.remove(0);
+            }
+        }
+    }
+    for &i in &empty {
+        if i > 0 && pieces[i - 1].1 && !pieces[i - 1].0.is_empty() && is_separator(&pieces[i - 1].0)
+        {
+            pieces[i - 1].0.clear();
+        } else if pieces[..i].iter().all(|(piece, _)| piece.is_empty()) {
+            if let Some((next, _)) = pieces.get_mut(i + 1) {
+                *next = next.trim_start_matches([' ', '.', '-', '_']).to_string();
+            }
+        }
+    }
+    pieces.into_iter().map(|(piece, _)| piece).collect()
+}
@@ -0,0 +1,545 @@
+// src/app/ui_tests.rs
+//! Drives the assignment and confirmation windows with simulated input.
+//!
+//! Every test compares the accessibility tree against a text snapshot in
+//! `tests/snapshots/`. With the `ui-snapshots` feature the rendered frame is
+//! compared against a PNG snapshot as well, which needs a Vulkan driver
+//! (lavapipe works without a GPU). Run with `UPDATE_SNAPSHOTS=1` to accept
+//! changes.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use egui_kittest::kittest::{by, Node, Queryable};
+use egui_kittest::Harness;
+
+use super::batch::{BatchJob, JobStatus};
+use super::{duplicates, probe, profile, Episode, LocalFile, SeriesRenamer};
+
+const SNAPSHOT_DIR: &str = "tests/snapshots";
+
+fn episode(number: u32, title: &str) -> Episode {
+    Episode {
+        title: title.to_string(),
+        episode: number.to_string(),
+        imdb_id: format!("tt9{:06}", number),
+    }
+}
+
+/// An app that has fetched a season and scanned `directory`, with the
+/// assignment window open.
+fn fetched_app(directory: &Path, file_names: &[&str]) -> SeriesRenamer {
+    let files = file_names
+        .iter()
+        .map(|name| {
+            let path = directory.join(name);
+            std::fs::write(&path, name).unwrap();
+            LocalFile { path, media: None }
+        })
+        .collect();
+    SeriesRenamer {
+        imdb_link: "https://www.imdb.com/title/tt0903747/".to_string(),
+        // Only shown in the main panel, a fixed value keeps the snapshots stable.
+        series_directory: "/series".to_string(),
+        show_process_window: true,
+        episodes: vec![
+            episode(1, "Pilot"),
+            episode(2, "Cat's in the Bag..."),
+            episode(3, "...And the Bag's in the River"),
+        ],
+        files,
+        fetch_status: "Fetched 3 episodes and 3 files.".to_string(),
+        ..Default::default()
+    }
+}
+
+fn harness(app: SeriesRenamer) -> Harness<'static, SeriesRenamer> {
+    Harness::builder()
+        .with_size([1000.0, 700.0])
+        .build_state(|ctx, app: &mut SeriesRenamer| app.ui(ctx), app)
+}
+
+/// Types an episode number into the input next to `file_name`.
+fn assign(harness: &mut Harness<'_, SeriesRenamer>, file_name: &str, number: &str) {
+    let input = harness.get_by_role_and_label(egui::accesskit::Role::TextInput, file_name);
+    input.focus();
+    input.type_text(number);
+    harness.run();
+}
+
+fn click(harness: &mut Harness<'_, SeriesRenamer>, label: &str) {
+    harness.get_by_label(label).click();
+    harness.run();
+}
+
+/// Runs frames until the title tags written in the background are done.
+fn wait_for_title_tags(harness: &mut Harness<'_, SeriesRenamer>) {
+    for _ in 0..500 {
+        if harness.state().tag_writers == 0 {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        harness.step();
+    }
+    panic!("The title tags were not written in time.");
+}
+
+fn has_text(harness: &Harness<'_, SeriesRenamer>, text: &str) -> bool {
+    harness.query_all_by_label(text).next().is_some()
+}
+
+/// Writes the accessibility tree as indented text. Unnamed containers and
+/// text runs are left out so the snapshot only changes when something
+/// visible does.
+fn write_tree(out: &mut String, node: Node<'_>, depth: usize) {
+    use egui::accesskit::Role;
+
+    if node.role() == Role::TextRun {
+        return;
+    }
+    let label = node.label().filter(|l| !l.is_empty());
+    let value = node.value().filter(|v| !v.is_empty());
+    let named = label.is_some() || value.is_some();
+    let depth = if named || !matches!(node.role(), Role::GenericContainer | Role::Unknown) {
+        let _ = write!(out, "{}{:?}", "  ".repeat(depth), node.role());
+        if let Some(label) = label {
+            let _ = write!(out, " {:?}", label);
+        }
+        if let Some(value) = value {
+            let _ = write!(out, " = {:?}", value);
+        }
+        if let Some(toggled) = node.toggled() {
+            let _ = write!(out, " [{:?}]", toggled);
+        }
+        if node.is_disabled() {
+            out.push_str(" [disabled]");
+        }
+        out.push('\n');
+        depth + 1
+    } else {
+        depth
+    };
+    for child in node.query_all(by().recursive(false)) {
+        write_tree(out, child, depth);
+    }
+}
+
+/// Compares the accessibility tree of the window titled `window` (and with
+/// `ui-snapshots` the whole rendered frame) with the snapshots stored under
+/// `name`.
+fn snapshot(harness: &mut Harness<'_, SeriesRenamer>, window: &str, name: &str) {
+    let mut tree = String::new();
+    write_tree(
+        &mut tree,
+        harness.get_by_role_and_label(egui::accesskit::Role::Window, window),
+        0,
+    );
+
+    let path = Path::new(SNAPSHOT_DIR).join(format!("{}.txt", name));
+    let new_path = path.with_extension("new.txt");
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(SNAPSHOT_DIR).unwrap();
+        std::fs::write(&path, &tree).unwrap();
+    } else {
+        let expected = std::fs::read_to_string(&path).unwrap_or_default();
+        if expected != tree {
+            std::fs::create_dir_all(SNAPSHOT_DIR).unwrap();
+            std::fs::write(&new_path, &tree).unwrap();
+            panic!(
+                "Accessibility tree differs from {}, the new tree was written to {}. \
+                 Run with UPDATE_SNAPSHOTS=1 to accept it.",
+                path.display(),
+                new_path.display()
+            );
+        }
+    }
+    let _ = std::fs::remove_file(new_path);
+
+    #[cfg(feature = "ui-snapshots")]
+    harness.snapshot(name);
+}
+
+#[test]
+fn assignment_window_previews_and_flags_inputs() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut harness = harness(fetched_app(
+        dir.path(),
+        &["track1.mkv", "track2.mkv", "track3.mkv"],
+    ));
+
+    assign(&mut harness, "track1.mkv", "1");
+    assign(&mut harness, "track2.mkv", "01");
+    assign(&mut harness, "track3.mkv", "x");
+
+    assert!(has_text(
+        &harness,
+        "-> S01E01 - Pilot.mkv (episode 1 is assigned more than once)"
+    ));
+    assert!(has_text(&harness, "Episode number may only contain digits"));
+    snapshot(
+        &mut harness,
+        "Assign Files to Episodes",
+        "assignment_window",
+    );
+
+    // Replace "01" with "2".
+    harness
+        .get_by_role_and_label(egui::accesskit::Role::TextInput, "track2.mkv")
+        .focus();
+    harness.run();
+    harness.press_key(egui::Key::Backspace);
+    harness.press_key(egui::Key::Backspace);
+    assign(&mut harness, "track2.mkv", "2");
+    assert!(has_text(&harness, "-> S01E02 - Cat's in the Bag.mkv"));
+    assert!(!has_text(
+        &harness,
+        "-> S01E01 - Pilot.mkv (episode 1 is assigned more than once)"
+    ));
+}
+
+#[test]
+fn unusable_inputs_open_the_validation_window() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut harness = harness(fetched_app(
+        dir.path(),
+        &["track1.mkv", "track2.mkv", "track3.mkv"],
+    ));
+
+    assign(&mut harness, "track1.mkv", "1");
+    assign(&mut harness, "track2.mkv", "9");
+    click(&mut harness, "Confirm Rename Plan");
+
+    assert!(harness.state().show_validation_dialog);
+    assert!(has_text(
+        &harness,
+        "'track2.mkv': episode 9 does not exist in season 1."
+    ));
+    snapshot(&mut harness, "Check Assignments", "validation_window");
+
+    click(&mut harness, "Back");
+    assert!(!harness.state().show_validation_dialog);
+    assert!(!harness.state().show_confirmation_dialog);
+}
+
+#[test]
+fn confirming_the_plan_renames_the_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut harness = harness(fetched_app(
+        dir.path(),
+        &["track1.mkv", "track2.mkv", "track3.mkv"],
+    ));
+
+    assign(&mut harness, "track1.mkv", "3");
+    assign(&mut harness, "track2.mkv", "1");
+    assign(&mut harness, "track3.mkv", "2");
+    click(&mut harness, "Confirm Rename Plan");
+
+    assert!(harness.state().show_confirmation_dialog);
+    assert!(has_text(
+        &harness,
+        "S01E03 - ...And the Bag's in the River.mkv"
+    ));
+    snapshot(&mut harness, "Confirm Renames", "confirmation_window");
+
+    click(&mut harness, "Confirm");
+
+    let state = harness.state();
+    assert!(!state.show_confirmation_dialog);
+    assert!(state.show_report_window);
+    assert_eq!(state.fetch_status, "Renamed 3 of 3 files (0 failed).");
+    let content = |name: &str| std::fs::read_to_string(dir.path().join(name)).unwrap();
+    assert_eq!(content("S01E01 - Pilot.mkv"), "track2.mkv");
+    assert_eq!(content("S01E02 - Cat's in the Bag.mkv"), "track3.mkv");
+    assert_eq!(
+        content("S01E03 - ...And the Bag's in the River.mkv"),
+        "track1.mkv"
+    );
+}
+
+#[test]
+fn cancelling_the_confirmation_keeps_the_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut harness = harness(fetched_app(
+        dir.path(),
+        &["track1.mkv", "track2.mkv", "track3.mkv"],
+    ));
+
+    assign(&mut harness, "track1.mkv", "1");
+    click(&mut harness, "Confirm Rename Plan");
+    harness
+        .get_by(|node| {
+            node.role() == egui::accesskit::Role::Button
+                && node.label().as_deref() == Some("Cancel")
+        })
+        .click();
+    harness.run();
+
+    assert!(!harness.state().show_confirmation_dialog);
+    assert!(harness.state().show_process_window);
+    assert!(dir.path().join("track1.mkv").exists());
+}
+
+#[test]
+fn active_profile_names_the_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut app = fetched_app(dir.path(), &["track1.mkv"]);
+    let mut profile = profile::SeriesProfile {
+        name: "Breaking Bad".to_string(),
+        imdb_link: app.imdb_link.clone(),
+        series_directory: app.series_directory.clone(),
+        ..Default::default()
+    };
+    profile.naming.template = "{title} ({season}x{episode:02})".to_string();
+    app.profiles = vec![profile];
+    app.active_profile = Some("Breaking Bad".to_string());
+    let mut harness = harness(app);
+
+    assign(&mut harness, "track1.mkv", "1");
+    assert!(has_text(&harness, "-> Pilot (1x01).mkv"));
+    assert!(has_text(&harness, "Process Season 2"));
+
+    // Without a profile the template from the settings applies again.
+    click(&mut harness, "Delete");
+    assert!(harness.state().profiles.is_empty());
+    assert!(has_text(&harness, "-> S01E01 - Pilot.mkv"));
+    assert!(!has_text(&harness, "Process Season 2"));
+}
+
+#[test]
+fn renaming_is_remembered_in_the_recent_menus() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut harness = harness(fetched_app(dir.path(), &["track1.mkv"]));
+
+    assign(&mut harness, "track1.mkv", "1");
+    click(&mut harness, "Confirm Rename Plan");
+    click(&mut harness, "Confirm");
+    let entry = &harness.state().history.entries[0];
+    assert_eq!(entry.series_directory, "/series");
+    assert_eq!(entry.outcome, "Renamed 1 of 1 files (0 failed).");
+
+    // Pick the directory again after working on something else.
+    harness.state_mut().series_directory.clear();
+    harness.state_mut().imdb_link.clear();
+    harness.state_mut().season_number = 4;
+    harness.get_all_by_label("Recent").nth(1).unwrap().click();
+    harness.run();
+    click(
+        &mut harness,
+        "/series\nSeason 1, just now: Renamed 1 of 1 files (0 failed).",
+    );
+
+    let state = harness.state();
+    assert_eq!(state.series_directory, "/series");
+    assert_eq!(state.imdb_link, "https://www.imdb.com/title/tt0903747/");
+    assert_eq!(state.season_number, 1);
+}
+
+/// A batch job that has fetched the test season and scanned `files`.
+fn ready_job(id: u64, directory: &Path, file_names: &[&str]) -> BatchJob {
+    let fetched = fetched_app(directory, file_names);
+    BatchJob {
+        status: JobStatus::Ready,
+        episodes: fetched.episodes,
+        files: fetched.files,
+        ..BatchJob::new(
+            id,
+            fetched.imdb_link,
+            1,
+            directory.to_string_lossy().to_string(),
+            Default::default(),
+        )
+    }
+}
+
+#[test]
+fn batch_jobs_are_reviewed_in_turn_and_applied_together() {
+    let (first, second) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let mut harness = harness(SeriesRenamer {
+        jobs: vec![
+            ready_job(0, first.path(), &["a.mkv"]),
+            ready_job(1, second.path(), &["b.mkv", "c.mkv"]),
+        ],
+        show_batch_window: true,
+        ..Default::default()
+    });
+    assert!(has_text(
+        &harness,
+        "0 of 2 job(s) reviewed, 2 ready for review, 0 fetching."
+    ));
+
+    click(&mut harness, "Review Next");
+    assign(&mut harness, "a.mkv", "1");
+    click(&mut harness, "Confirm Rename Plan");
+    click(&mut harness, "Save to Batch");
+
+    // The second job opens right away, nothing is renamed yet.
+    assert_eq!(harness.state().reviewing_job, Some(1));
+    assert!(first.path().join("a.mkv").exists());
+    assign(&mut harness, "b.mkv", "2");
+    assign(&mut harness, "c.mkv", "3");
+    click(&mut harness, "Confirm Rename Plan");
+    click(&mut harness, "Save to Batch");
+
+    assert!(!harness.state().show_process_window);
+    assert!(has_text(
+        &harness,
+        "2 of 2 job(s) reviewed, 0 ready for review, 0 fetching."
+    ));
+    click(&mut harness, "Apply Batch");
+
+    let state = harness.state();
+    assert!(state.jobs.is_empty());
+    assert_eq!(state.rename_report.len(), 3);
+    assert_eq!(
+        state.fetch_status,
+        "Applied 2 batch job(s). Renamed 3 of 3 files (0 failed)."
+    );
+    assert_eq!(state.history.entries.len(), 2);
+    assert!(first.path().join("S01E01 - Pilot.mkv").exists());
+    assert!(second.path().join("S01E02 - Cat's in the Bag.mkv").exists());
+    assert!(second
+        .path()
+        .join("S01E03 - ...And the Bag's in the River.mkv")
+        .exists());
+}
+
+#[test]
+fn sequential_assignment_can_be_corrected() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut harness = harness(fetched_app(
+        dir.path(),
+        &["track02.mkv", "track10.mkv", "notes.txt"],
+    ));
+
+    harness.state_mut().sequential_skip = "2".to_string();
+    click(&mut harness, "Assign Sequentially");
+    assert!(has_text(&harness, "-> S01E01 - Pilot.mkv"));
+    assert!(has_text(
+        &harness,
+        "-> S01E03 - ...And the Bag's in the River.mkv"
+    ));
+    assert!(has_text(
+        &harness,
+        "Assigned 2 video files. Check the result and correct single rows if needed."
+    ));
+
+    // Correct one row by hand.
+    harness
+        .get_by_role_and_label(egui::accesskit::Role::TextInput, "track10.mkv")
+        .focus();
+    harness.run();
+    harness.press_key(egui::Key::Backspace);
+    assign(&mut harness, "track10.mkv", "2");
+    let inputs = &harness.state().file_episode_inputs;
+    assert_eq!(inputs[&dir.path().join("track02.mkv")], "1");
+    assert_eq!(inputs[&dir.path().join("track10.mkv")], "2");
+}
+
+#[test]
+fn title_tags_suggest_episodes() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut app = fetched_app(dir.path(), &["track1.mkv"]);
+    app.files[0].media = Some(probe::MediaInfo {
+        title: Some("Breaking.Bad.S01E03.720p".to_string()),
+        ..Default::default()
+    });
+    app.settings.write_title_tags = true;
+    let mut harness = harness(app);
+
+    assign(&mut harness, "track1.mkv", "1");
+    assert!(has_text(&harness, "Title tag: Breaking.Bad.S01E03.720p"));
+    assert!(has_text(&harness, "The title tag names episode 3."));
+    click(&mut harness, "Use E3");
+    assert!(has_text(
+        &harness,
+        "-> S01E03 - ...And the Bag's in the River.mkv"
+    ));
+    assert!(!has_text(&harness, "The title tag names episode 3."));
+
+    // The file is not a real MKV, so its tag cannot be written, but it is renamed anyway.
+    click(&mut harness, "Confirm Rename Plan");
+    // The tags are written in the background, which keeps the UI repainting.
+    harness.get_by_label("Confirm").click();
+    harness.step();
+    wait_for_title_tags(&mut harness);
+    let state = harness.state();
+    assert_eq!(state.fetch_status, "Renamed 1 of 1 files (0 failed).");
+    assert!(state.report_status.starts_with(
+        "Could not write the title tag of 1 file(s). 'S01E03 - ...And the Bag's in the River.mkv':"
+    ));
+}
+
+#[test]
+fn retried_renames_get_title_tags() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut app = fetched_app(dir.path(), &["track1.mkv"]);
+    app.settings.write_title_tags = true;
+    let blocker = dir.path().join("S01E01 - Pilot.mkv");
+    std::fs::write(&blocker, "blocker").unwrap();
+    let mut harness = harness(app);
+
+    assign(&mut harness, "track1.mkv", "1");
+    click(&mut harness, "Confirm Rename Plan");
+    click(&mut harness, "Confirm");
+    assert_eq!(
+        harness.state().fetch_status,
+        "Renamed 0 of 1 files (1 failed)."
+    );
+
+    std::fs::remove_file(&blocker).unwrap();
+    harness.get_by_label("Retry Failed").click();
+    harness.step();
+    wait_for_title_tags(&mut harness);
+    let state = harness.state();
+    assert_eq!(state.fetch_status, "Renamed 1 of 1 files (0 failed).");
+    assert!(state
+        .report_status
+        .starts_with("Could not write the title tag of 1 file(s). 'S01E01 - Pilot.mkv':"));
+}
+
+#[test]
+fn extra_copies_are_moved_to_the_duplicates_folder() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut app = fetched_app(
+        dir.path(),
+        &[
+            "Show.S01E01.720p.mkv",
+            "Show.S01E01.1080p.mkv",
+            "Show.S01E02.mkv",
+        ],
+    );
+    app.series_directory = dir.path().to_string_lossy().to_string();
+    app.duplicate_groups = duplicates::find_duplicates(&app.files);
+    let mut harness = harness(app);
+
+    assert!(has_text(&harness, "Duplicates (1)"));
+    assert!(has_text(&harness, "  Keep: Show.S01E01.1080p.mkv (0.0 MB)"));
+    assign(&mut harness, "Show.S01E01.720p.mkv", "1");
+    click(&mut harness, "Move Extra Copies");
+
+    // Nothing is moved before the confirmation.
+    assert!(dir.path().join("Show.S01E01.720p.mkv").exists());
+    click(&mut harness, "Move");
+    let state = harness.state();
+    assert!(dir.path().join("Duplicates/Show.S01E01.720p.mkv").exists());
+    assert_eq!(state.files.len(), 2);
+    assert!(state.duplicate_groups.is_empty());
+    assert!(!state
+        .file_episode_inputs
+        .contains_key(&dir.path().join("Show.S01E01.720p.mkv")));
+    assert_eq!(state.rename_report.len(), 1);
+    assert_eq!(
+        state.fetch_status,
+        format!(
+            "Moved 1 extra copy to '{}'.",
+            dir.path().join("Duplicates").display()
+        )
+    );
+
+    click(&mut harness, "Undo Moves");
+    let state = harness.state();
+    assert!(dir.path().join("Show.S01E01.720p.mkv").exists());
+    assert_eq!(state.files.len(), 3);
+    assert_eq!(state.duplicate_groups.len(), 1);
+    assert!(has_text(&harness, "Moved 1 copy back."));
+}
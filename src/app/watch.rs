@@ -0,0 +1,650 @@
+// src/app/watch.rs
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::omdb::{self, CancelToken, OmdbError};
+use super::parse::{self, EpisodePattern};
+use super::plan::{normalize_episode_number, target_file_name};
+use super::rename::RenameOperation;
+use super::sanitize::NamingOptions;
+use super::scan::imdb_id_from_link;
+use super::settings::AppSettings;
+use super::{Episode, LocalFile};
+
+/// A directory the daemon watches for new episodes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchFolder {
+    pub directory: String,
+    /// Series all files in the directory belong to. If empty, the series is
+    /// looked up by the name in front of the episode number.
+    pub imdb_link: String,
+    /// Renamed files are moved to `<series>/Season <nn>/` below this
+    /// directory. If empty, they are renamed in place.
+    pub target_directory: String,
+}
+
+/// A file the daemon did not rename on its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewItem {
+    pub path: PathBuf,
+    /// Why the file needs a look.
+    pub reason: String,
+    /// Where the file would have been moved, if a guess was possible.
+    pub suggestion: Option<PathBuf>,
+    /// Series and season of the guess, to prefill the manual assignment.
+    pub imdb_id: Option<String>,
+    pub season: Option<u32>,
+}
+
+/// Files waiting for review, shared between the daemon and the app through
+/// a JSON file next to the settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewQueue {
+    pub items: Vec<ReviewItem>,
+}
+
+impl ReviewQueue {
+    pub fn file_path() -> Result<PathBuf, String> {
+        let settings_path = AppSettings::file_path().map_err(|e| e.to_string())?;
+        Ok(settings_path.with_file_name("review_queue.json"))
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Writes the queue through a temporary file, so a reader never sees a
+    /// half written queue.
+    pub fn store_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, json).map_err(|e| e.to_string())?;
+        std::fs::rename(&temporary, path).map_err(|e| e.to_string())
+    }
+
+    /// Loads the queue from disk, changes it and writes it back. Both the
+    /// daemon and the app change the queue, so it is never kept in memory
+    /// between changes, and a lock file keeps them from changing it at the
+    /// same time.
+    pub fn update(path: &Path, change: impl FnOnce(&mut Self)) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let _lock = QueueLock::acquire(path)?;
+        let mut queue = Self::load_from(path)?;
+        change(&mut queue);
+        queue.store_to(path)?;
+        Ok(queue)
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.items.iter().any(|item| item.path == path)
+    }
+
+    /// Adds an item, replacing an older one for the same file.
+    pub fn push(&mut self, item: ReviewItem) {
+        self.remove(&item.path);
+        self.items.push(item);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.items.retain(|item| item.path != path);
+    }
+}
+
+/// How long [`ReviewQueue::update`] waits for another process to finish.
+const LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+/// Age after which a lock is assumed to be left over from a crashed process.
+const LOCK_STALE: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Exclusive access to the review queue, held as long as the lock file
+/// next to it exists.
+struct QueueLock {
+    path: PathBuf,
+}
+
+impl QueueLock {
+    fn acquire(queue_path: &Path) -> Result<Self, String> {
+        let path = queue_path.with_extension("json.lock");
+        let started = std::time::Instant::now();
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let age = std::fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok());
+                    if age.is_some_and(|age| age > LOCK_STALE) {
+                        let _ = std::fs::remove_file(&path);
+                    } else if started.elapsed() > LOCK_TIMEOUT {
+                        return Err("The review queue is locked by another process.".to_string());
+                    } else {
+                        std::thread::sleep(std::time::Duration::from_millis(10));
+                    }
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+}
+
+impl Drop for QueueLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// What to do with a new file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// Series, season and episode are certain.
+    Rename(RenameOperation),
+    /// Something is uncertain, a human has to decide.
+    Review(ReviewItem),
+    /// Not an episode, not finished downloading or already named correctly.
+    Ignore,
+    /// A temporary problem like a network error, try again later.
+    Retry(String),
+}
+
+/// Identifies new files, caching OMDb lookups between files.
+pub struct Identifier {
+    client: omdb::Client,
+    naming: NamingOptions,
+    series_by_name: HashMap<String, omdb::Series>,
+    series_by_id: HashMap<String, omdb::Series>,
+    seasons: HashMap<(String, u32), Vec<Episode>>,
+}
+
+impl Identifier {
+    pub fn new(client: omdb::Client, naming: NamingOptions) -> Self {
+        Self {
+            client,
+            naming,
+            series_by_name: HashMap::new(),
+            series_by_id: HashMap::new(),
+            seasons: HashMap::new(),
+        }
+    }
+
+    /// Decides what to do with a file that appeared in `folder`. Blocks on
+    /// OMDb requests.
+    pub fn decide(&mut self, folder: &WatchFolder, path: &Path, cancel: &CancelToken) -> Decision {
+        if !parse::is_video_file(path) || parse::is_partial_download(path) {
+            return Decision::Ignore;
+        }
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let review = |reason: String,
+                      suggestion: Option<PathBuf>,
+                      imdb_id: Option<&str>,
+                      season: Option<u32>| {
+            Decision::Review(ReviewItem {
+                path: path.to_path_buf(),
+                reason,
+                suggestion,
+                imdb_id: imdb_id.map(str::to_string),
+                season,
+            })
+        };
+        let failure = |error: OmdbError| match error {
+            error if error.is_transient() || matches!(error, OmdbError::RateLimited(_)) => {
+                Decision::Retry(error.to_string())
+            }
+            error => review(error.with_guidance().replace('\n', " "), None, None, None),
+        };
+
+        let Some(parsed) = parse::parse_episode_name(&file_name) else {
+            return review(
+                "No season and episode number found in the name.".to_string(),
+                None,
+                None,
+                None,
+            );
+        };
+
+        // Anything that makes the match uncertain sends the file to review.
+        let mut doubts = Vec::new();
+        let series = if !folder.imdb_link.trim().is_empty() {
+            let Some(imdb_id) = imdb_id_from_link(&folder.imdb_link) else {
+                return review(
+                    "The IMDb link of the watch folder contains no IMDb ID.".to_string(),
+                    None,
+                    None,
+                    None,
+                );
+            };
+            match self.series_by_id(&imdb_id, cancel) {
+                Ok(series) => series,
+                Err(e) => return failure(e),
+            }
+        } else if parsed.series.is_empty() {
+            return review(
+                "The name contains no series name and the watch folder has no series set."
+                    .to_string(),
+                None,
+                None,
+                Some(parsed.season),
+            );
+        } else {
+            match self.find_series(&parsed.series, cancel) {
+                Ok(series) => {
+                    if parse::normalize_title(&series.title)
+                        != parse::normalize_title(&parsed.series)
+                    {
+                        doubts.push(format!(
+                            "'{}' was matched to the series '{}'.",
+                            parsed.series, series.title
+                        ));
+                    }
+                    series
+                }
+                Err(e) => return failure(e),
+            }
+        };
+
+        let episodes = match self.season(&series.imdb_id, parsed.season, cancel) {
+            Ok(episodes) => episodes,
+            Err(e) => return failure(e),
+        };
+        let number = parsed.episodes[0].to_string();
+        let Some(episode) = episodes
+            .iter()
+            .find(|e| normalize_episode_number(&e.episode).as_deref() == Some(number.as_str()))
+        else {
+            return review(
+                format!(
+                    "Episode {} does not exist in season {} of '{}'.",
+                    number, parsed.season, series.title
+                ),
+                None,
+                Some(&series.imdb_id),
+                Some(parsed.season),
+            );
+        };
+
+        if parsed.pattern != EpisodePattern::SeasonEpisode {
+            doubts.push("The episode number is not written as SxxEyy.".to_string());
+        }
+        if parsed.episodes.len() > 1 {
+            doubts.push("The file contains several episodes.".to_string());
+        }
+        let target = match self.target_path(folder, &series, parsed.season, episode, path) {
+            Ok(target) => target,
+            Err(e) => {
+                return review(
+                    format!("Could not name the file: {}.", e),
+                    None,
+                    Some(&series.imdb_id),
+                    Some(parsed.season),
+                )
+            }
+        };
+        if target == path {
+            return Decision::Ignore;
+        }
+        if target.exists() {
+            doubts.push("A file with the new name already exists.".to_string());
+        }
+
+        if doubts.is_empty() {
+            Decision::Rename(RenameOperation {
+                from: path.to_path_buf(),
+                to: target,
+            })
+        } else {
+            review(
+                doubts.join(" "),
+                Some(target),
+                Some(&series.imdb_id),
+                Some(parsed.season),
+            )
+        }
+    }
+
+    fn find_series(&mut self, name: &str, cancel: &CancelToken) -> Result<omdb::Series, OmdbError> {
+        let key = parse::normalize_title(name);
+        if let Some(series) = self.series_by_name.get(&key) {
+            return Ok(series.clone());
+        }
+        let series = self.client.find_series(name, cancel)?;
+        self.series_by_name.insert(key, series.clone());
+        Ok(series)
+    }
+
+    fn series_by_id(
+        &mut self,
+        imdb_id: &str,
+        cancel: &CancelToken,
+    ) -> Result<omdb::Series, OmdbError> {
+        if let Some(series) = self.series_by_id.get(imdb_id) {
+            return Ok(series.clone());
+        }
+        let mut series = self.client.series_by_id(imdb_id, cancel)?;
+        series.imdb_id = imdb_id.to_string();
+        self.series_by_id
+            .insert(imdb_id.to_string(), series.clone());
+        Ok(series)
+    }
+
+    fn season(
+        &mut self,
+        imdb_id: &str,
+        season: u32,
+        cancel: &CancelToken,
+    ) -> Result<Vec<Episode>, OmdbError> {
+        let key = (imdb_id.to_string(), season);
+        if let Some(episodes) = self.seasons.get(&key) {
+            return Ok(episodes.clone());
+        }
+        let episodes = self
+            .client
+            .fetch_season(imdb_id, season, cancel, &|_, _| {})?;
+        self.seasons.insert(key, episodes.clone());
+        Ok(episodes)
+    }
+
+    fn target_path(
+        &self,
+        folder: &WatchFolder,
+        series: &omdb::Series,
+        season: u32,
+        episode: &Episode,
+        path: &Path,
+    ) -> Result<PathBuf, String> {
+        let directory = if folder.target_directory.trim().is_empty() {
+            path.parent().map(Path::to_path_buf).unwrap_or_default()
+        } else {
+            Path::new(folder.target_directory.trim())
+                .join(self.naming.policy.sanitize(&series.title))
+                .join(format!("Season {:02}", season))
+        };
+        let file = LocalFile {
+            path: directory.join(path.file_name().unwrap_or_default()),
+            media: None,
+        };
+        Ok(directory.join(target_file_name(season, &self.naming, episode, &file)?))
+    }
+}
+
+/// Renames or moves a file, creating the target directory. Never overwrites
+/// an existing file. Falls back to copying when the target is on another
+/// file system, any other error is returned.
+pub fn move_file(operation: &RenameOperation) -> Result<(), String> {
+    let RenameOperation { from, to } = operation;
+    if to.exists() {
+        return Err(format!("'{}' already exists", to.display()));
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    match std::fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(e) if !crosses_devices(&e) => return Err(e.to_string()),
+        Err(_) => {}
+    }
+    // Creating the copy fails if a file appeared at the target since the
+    // check above, instead of overwriting it.
+    let mut target = match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)
+    {
+        Ok(target) => target,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(format!("'{}' already exists", to.display()));
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+    if let Err(e) = copy_contents(from, &mut target) {
+        drop(target);
+        let _ = std::fs::remove_file(to);
+        return Err(e.to_string());
+    }
+    std::fs::remove_file(from)
+        .map_err(|e| format!("Copied, but could not remove the original: {}", e))
+}
+
+/// Copies the content and permissions of `from` into the new file `target`.
+fn copy_contents(from: &Path, target: &mut std::fs::File) -> std::io::Result<()> {
+    let mut source = std::fs::File::open(from)?;
+    std::io::copy(&mut source, target)?;
+    target.set_permissions(source.metadata()?.permissions())?;
+    target.sync_all()
+}
+
+/// Whether a rename failed because the target is on another file system.
+fn crosses_devices(error: &std::io::Error) -> bool {
+    // EXDEV on Linux and macOS, ERROR_NOT_SAME_DEVICE on Windows.
+    let code = if cfg!(windows) { 17 } else { 18 };
+    error.raw_os_error() == Some(code)
+}
+
+/// A file seen by the watcher that may still be growing.
+#[cfg(not(target_arch = "wasm32"))]
+struct PendingFile {
+    folder: usize,
+    size: u64,
+    /// When the size last changed, or when to try again after a failure.
+    since: std::time::Instant,
+}
+
+/// How long to wait before trying a file again after a temporary error.
+#[cfg(not(target_arch = "wasm32"))]
+const RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long the events of a file the watcher renamed itself are ignored.
+#[cfg(not(target_arch = "wasm32"))]
+const PRODUCED_MEMORY: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Watches `folders` until cancelled, renaming new episodes and adding
+/// uncertain ones to the review queue at `queue_path`. Files already in the
+/// folders are handled as if they had just been added. A file is handled
+/// once its size has not changed for `settle`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn watch(
+    folders: &[WatchFolder],
+    settle: std::time::Duration,
+    mut identifier: Identifier,
+    queue_path: &Path,
+    cancel: &CancelToken,
+) -> Result<(), String> {
+    use notify::Watcher as _;
+    use std::time::{Duration, Instant};
+
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    })
+    .map_err(|e| e.to_string())?;
+    for folder in folders {
+        watcher
+            .watch(
+                Path::new(&folder.directory),
+                notify::RecursiveMode::Recursive,
+            )
+            .map_err(|e| format!("Cannot watch '{}': {}", folder.directory, e))?;
+        log::info!("Watching '{}'", folder.directory);
+    }
+
+    let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+    // Files that arrived while the daemon was not running.
+    for (index, folder) in folders.iter().enumerate() {
+        let files = walkdir::WalkDir::new(&folder.directory)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file());
+        for entry in files {
+            let path = entry.into_path();
+            if folder_of(folders, &path) != Some(index) {
+                continue;
+            }
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            let since = Instant::now();
+            pending.insert(
+                path,
+                PendingFile {
+                    folder: index,
+                    size,
+                    since,
+                },
+            );
+        }
+    }
+    // Files we renamed ourselves show up as new files too, for a while.
+    let mut produced: HashMap<PathBuf, Instant> = HashMap::new();
+
+    while !cancel.is_cancelled() {
+        produced.retain(|_, renamed| renamed.elapsed() < PRODUCED_MEMORY);
+        match receiver.recv_timeout(Duration::from_secs(1)) {
+            Ok(Ok(event)) => {
+                if !matches!(
+                    event.kind,
+                    notify::EventKind::Create(_) | notify::EventKind::Modify(_)
+                ) {
+                    continue;
+                }
+                for path in event.paths {
+                    let Some(folder) = folder_of(folders, &path) else {
+                        continue;
+                    };
+                    if produced.contains_key(&path) || !path.is_file() {
+                        continue;
+                    }
+                    let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                    pending.insert(
+                        path,
+                        PendingFile {
+                            folder,
+                            size,
+                            since: Instant::now(),
+                        },
+                    );
+                }
+            }
+            Ok(Err(e)) => log::warn!("Watch error: {}", e),
+            Err(crossbeam_channel::RecvTimeoutError::Timeout) => {}
+            Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
+                return Err("The file watcher stopped.".to_string());
+            }
+        }
+
+        let now = Instant::now();
+        let settled: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, file)| now.saturating_duration_since(file.since) >= settle)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in settled {
+            let Some(mut file) = pending.remove(&path) else {
+                continue;
+            };
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue; // Deleted or moved away in the meantime.
+            };
+            if metadata.len() != file.size {
+                file.size = metadata.len();
+                file.since = now;
+                pending.insert(path, file);
+                continue;
+            }
+            if ReviewQueue::load_from(queue_path).is_ok_and(|queue| queue.contains(&path)) {
+                continue;
+            }
+
+            let folder = &folders[file.folder];
+            let review = match identifier.decide(folder, &path, cancel) {
+                Decision::Ignore => continue,
+                Decision::Retry(reason) => {
+                    log::warn!("'{}': {} Trying again later.", path.display(), reason);
+                    file.since = now + RETRY_DELAY;
+                    pending.insert(path, file);
+                    continue;
+                }
+                Decision::Rename(operation) => match move_file(&operation) {
+                    Ok(()) => {
+                        log::info!(
+                            "Renamed '{}' to '{}'",
+                            operation.from.display(),
+                            operation.to.display()
+                        );
+                        produced.insert(operation.to, Instant::now());
+                        continue;
+                    }
+                    Err(e) => ReviewItem {
+                        path: path.clone(),
+                        reason: format!("Renaming failed: {}", e),
+                        suggestion: Some(operation.to),
+                        imdb_id: None,
+                        season: None,
+                    },
+                },
+                Decision::Review(item) => item,
+            };
+            log::info!("Queued '{}' for review: {}", path.display(), review.reason);
+            if let Err(e) = ReviewQueue::update(queue_path, |queue| queue.push(review)) {
+                log::error!("Could not write the review queue: {}", e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Index of the watch folder containing `path`, preferring the innermost.
+#[cfg(not(target_arch = "wasm32"))]
+fn folder_of(folders: &[WatchFolder], path: &Path) -> Option<usize> {
+    folders
+        .iter()
+        .enumerate()
+        .filter(|(_, folder)| path.starts_with(&folder.directory))
+        .max_by_key(|(_, folder)| folder.directory.len())
+        .map(|(index, _)| index)
+}
+
+/// Entry point of `series_renamer --watch`: loads the settings and API key
+/// and watches until the process is stopped.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_daemon() -> Result<(), String> {
+    let settings = AppSettings::load().map_err(|e| format!("Error loading config: {}", e))?;
+    let problems = settings.validate();
+    if !problems.is_empty() {
+        return Err(problems.join(" "));
+    }
+    if settings.watch_folders.is_empty() {
+        return Err(
+            "No watch folders are configured. Add them in the app under Settings.".to_string(),
+        );
+    }
+
+    let store = super::secrets::default_store()?;
+    let api_key = store.load()?.unwrap_or_else(|| settings.api_key.clone());
+    let api_key = api_key.trim();
+    if api_key.is_empty() || api_key == super::settings::PLACEHOLDER_API_KEY {
+        return Err("No OMDb API key is set. Start the app once to set one up.".to_string());
+    }
+
+    watch(
+        &settings.watch_folders,
+        std::time::Duration::from_secs(settings.watch_settle_secs),
+        Identifier::new(settings.omdb_client(api_key), settings.naming),
+        &ReviewQueue::file_path()?,
+        &CancelToken::default(),
+    )
+}
@@ -1,4 +1,11 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
-pub use app::SeriesRenamer;
+pub use app::{watch, SeriesRenamer};
+
+// For the integration tests in `tests/`, not meant as a library interface.
+#[doc(hidden)]
+pub use app::{
+    duplicates, history, omdb, parse, plan, probe, profile, rename, sanitize, scan, secrets,
+    template, Episode, LocalFile,
+};
@@ -1,9 +1,20 @@
 #![warn(clippy::all, rust_2018_idioms)]
-#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
+// Hide the console window on Windows in release.
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result {
+    // `--watch` runs without a window and renames new episodes in the watch folders.
+    if std::env::args().any(|arg| arg == "--watch") {
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+        if let Err(e) = series_renamer::watch::run_daemon() {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
     let native_options = eframe::NativeOptions {
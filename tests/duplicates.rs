@@ -0,0 +1,138 @@
+//! Tests of finding copies of the same episode and moving the extra ones.
+
+mod support;
+
+use series_renamer::duplicates::{
+    find_duplicates, move_files, plan_moves, resolve_folder, KeepRule, DEFAULT_FOLDER,
+};
+use series_renamer::rename::RenameStatus;
+use series_renamer::scan::scan_directory;
+use support::SeriesDir;
+
+fn names(group: &series_renamer::duplicates::DuplicateGroup) -> Vec<String> {
+    group
+        .files
+        .iter()
+        .map(|copy| {
+            copy.file
+                .path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn copies_are_found_by_episode_number_and_content() {
+    let dir = SeriesDir::with_files(&[
+        "Show.S01E01.720p.HDTV.x264-A.mkv",
+        "Show.S01E01.1080p.WEB-DL.x264-B.mkv",
+        "Show.S01E02.720p.HDTV.x264-A.mkv",
+        "track3.mkv",
+        "copy of track3.mkv",
+        "notes.txt",
+        "notes copy.txt",
+    ]);
+    // Same content under unrelated names, other files of the same size differ.
+    std::fs::write(dir.join("track3.mkv"), "same content").unwrap();
+    std::fs::write(dir.join("copy of track3.mkv"), "same content").unwrap();
+    std::fs::write(dir.join("notes.txt"), "same content").unwrap();
+    std::fs::write(dir.join("notes copy.txt"), "same content").unwrap();
+
+    let groups = find_duplicates(&scan_directory(dir.path()));
+    assert_eq!(groups.len(), 2);
+    assert_eq!(names(&groups[0]), ["copy of track3.mkv", "track3.mkv"]);
+    assert_eq!(groups[0].label(), "Identical content");
+    assert!(groups[0].identical);
+    assert_eq!(
+        names(&groups[1]),
+        [
+            "Show.S01E01.720p.HDTV.x264-A.mkv",
+            "Show.S01E01.1080p.WEB-DL.x264-B.mkv"
+        ]
+    );
+    assert_eq!(groups[1].label(), "S01E01");
+    assert!(!groups[1].identical);
+}
+
+#[test]
+fn keep_rules_pick_the_best_copy() {
+    let dir = SeriesDir::with_files(&[
+        "Show.S01E01.1080p.WEB-DL-A.mkv",
+        "Show.S01E01.720p.PROPER.HDTV-B.mkv",
+        "Show.S01E01.720p.HDTV-C.mkv",
+    ]);
+    std::fs::write(dir.join("Show.S01E01.720p.HDTV-C.mkv"), vec![0u8; 4096]).unwrap();
+
+    let groups = find_duplicates(&scan_directory(dir.path()));
+    let group = &groups[0];
+    let kept = |rule| names(group)[group.best(rule)].clone();
+    assert_eq!(
+        kept(KeepRule::HigherResolution),
+        "Show.S01E01.1080p.WEB-DL-A.mkv"
+    );
+    assert_eq!(kept(KeepRule::Proper), "Show.S01E01.720p.PROPER.HDTV-B.mkv");
+    assert_eq!(kept(KeepRule::Largest), "Show.S01E01.720p.HDTV-C.mkv");
+}
+
+#[test]
+fn extra_copies_are_moved_without_overwriting() {
+    let dir = SeriesDir::with_files(&["a.mkv", "sub/a.mkv", "Duplicates/a.mkv"]);
+    let folder = resolve_folder(dir.path(), " ");
+    assert_eq!(folder, dir.join(DEFAULT_FOLDER));
+    assert_eq!(
+        resolve_folder(dir.path(), "/elsewhere"),
+        std::path::Path::new("/elsewhere")
+    );
+
+    let operations = plan_moves(
+        &[
+            dir.join("a.mkv"),
+            dir.join("sub/a.mkv"),
+            dir.join("missing.mkv"),
+        ],
+        &folder,
+    );
+    let targets: Vec<_> = operations.iter().map(|o| o.to.clone()).collect();
+    assert_eq!(
+        targets,
+        [
+            folder.join("a (2).mkv"),
+            folder.join("a (3).mkv"),
+            folder.join("missing.mkv")
+        ]
+    );
+
+    let outcomes = move_files(&operations);
+    let statuses: Vec<_> = outcomes.iter().map(|o| o.status).collect();
+    assert_eq!(
+        statuses,
+        [
+            RenameStatus::Renamed,
+            RenameStatus::Renamed,
+            RenameStatus::Failed
+        ]
+    );
+    assert_eq!(dir.content("Duplicates/a.mkv"), "Duplicates/a.mkv");
+    assert_eq!(dir.content("Duplicates/a (2).mkv"), "a.mkv");
+    assert_eq!(dir.content("Duplicates/a (3).mkv"), "sub/a.mkv");
+}
+
+#[test]
+fn equal_samples_are_confirmed_by_comparing_completely() {
+    let dir = SeriesDir::with_files(&["a.mkv", "b.mkv", "c.mkv"]);
+    // Only the bytes between the start and middle samples differ.
+    let content = vec![7u8; 4 * 1024 * 1024];
+    let mut other = content.clone();
+    other[1200 * 1024] = 8;
+    std::fs::write(dir.join("a.mkv"), &content).unwrap();
+    std::fs::write(dir.join("b.mkv"), &other).unwrap();
+    std::fs::write(dir.join("c.mkv"), &content).unwrap();
+
+    let groups = find_duplicates(&scan_directory(dir.path()));
+    assert_eq!(groups.len(), 1);
+    assert_eq!(names(&groups[0]), ["a.mkv", "c.mkv"]);
+    assert!(groups[0].identical);
+}
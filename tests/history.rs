@@ -0,0 +1,58 @@
+//! Tests of the recently processed directories and series.
+
+use series_renamer::history::{age_label, History, HistoryEntry, MAX_ENTRIES};
+
+fn entry(directory: &str, imdb_link: &str, timestamp: u64) -> HistoryEntry {
+    HistoryEntry {
+        series_directory: directory.to_string(),
+        imdb_link: imdb_link.to_string(),
+        season_number: 1,
+        timestamp,
+        outcome: "Renamed 1 of 1 files (0 failed).".to_string(),
+    }
+}
+
+#[test]
+fn newest_entry_replaces_the_same_combination() {
+    let mut history = History::default();
+    history.record(entry("/a", "tt1", 1));
+    history.record(entry("/b", "tt1", 2));
+    history.record(entry("/a", "tt2", 3));
+    history.record(entry("/a", "tt1", 4));
+
+    let order: Vec<(&str, &str)> = history
+        .entries
+        .iter()
+        .map(|e| (e.series_directory.as_str(), e.imdb_link.as_str()))
+        .collect();
+    assert_eq!(order, [("/a", "tt1"), ("/a", "tt2"), ("/b", "tt1")]);
+
+    let series: Vec<u64> = history.series().iter().map(|e| e.timestamp).collect();
+    assert_eq!(series, [4, 3]);
+}
+
+#[test]
+fn oldest_entries_are_forgotten() {
+    let mut history = History::default();
+    for i in 0..MAX_ENTRIES as u64 + 5 {
+        history.record(entry(&format!("/{}", i), "tt1", i));
+    }
+    assert_eq!(history.entries.len(), MAX_ENTRIES);
+    assert_eq!(
+        history.entries[0].series_directory,
+        format!("/{}", MAX_ENTRIES + 4)
+    );
+    assert_eq!(history.entries.last().unwrap().series_directory, "/5");
+}
+
+#[test]
+fn ages_are_rounded_down_to_the_largest_unit() {
+    let now = 100_000_000;
+    assert_eq!(age_label(now - 30, now), "just now");
+    assert_eq!(age_label(now - 60, now), "1 minute ago");
+    assert_eq!(age_label(now - 2 * 3_600 - 59, now), "2 hours ago");
+    assert_eq!(age_label(now - 3 * 86_400, now), "3 days ago");
+    assert_eq!(age_label(now - 70 * 86_400, now), "2 months ago");
+    // Clocks can go backwards.
+    assert_eq!(age_label(now + 10, now), "just now");
+}
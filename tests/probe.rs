@@ -0,0 +1,393 @@
+//! Tests of reading media details from synthetic Matroska and MP4 headers,
+//! of writing their title tags and of the episode length checks.
+
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use series_renamer::omdb::Series;
+use series_renamer::probe::{
+    check_duration, duration_warning, expected_duration, probe, probe_file, write_title,
+    DurationCheck, MediaInfo,
+};
+use series_renamer::LocalFile;
+
+/// Encodes an EBML element with an eight byte size.
+fn element(id: u32, payload: &[u8]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = id
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|b| *b == 0)
+        .collect();
+    bytes.push(0x01);
+    bytes.extend_from_slice(&(payload.len() as u64).to_be_bytes()[1..]);
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+fn uint(id: u32, value: u64) -> Vec<u8> {
+    element(id, &value.to_be_bytes())
+}
+
+fn ebml_header() -> Vec<u8> {
+    element(0x1A45_DFA3, &element(0x4282, b"matroska"))
+}
+
+fn info(seconds: f64) -> Vec<u8> {
+    info_with_title(seconds, None)
+}
+
+fn info_with_title(seconds: f64, title: Option<&str>) -> Vec<u8> {
+    let mut payload = uint(0x2A_D7B1, 1_000_000);
+    payload.extend(element(0x4489, &(seconds * 1000.0).to_be_bytes()));
+    if let Some(title) = title {
+        payload.extend(element(0x7BA9, title.as_bytes()));
+    }
+    element(0x1549_A966, &payload)
+}
+
+fn tracks() -> Vec<u8> {
+    let mut video = uint(0x83, 1);
+    video.extend(element(0x86, b"V_MPEGH/ISO/HEVC"));
+    let mut dimensions = uint(0xB0, 1920);
+    dimensions.extend(uint(0xBA, 1080));
+    video.extend(element(0xE0, &dimensions));
+    let mut audio = uint(0x83, 2);
+    audio.extend(element(0x86, b"A_AAC"));
+    let mut payload = element(0xAE, &video);
+    payload.extend(element(0xAE, &audio));
+    payload.extend(element(0xAE, &audio));
+    element(0x1654_AE6B, &payload)
+}
+
+fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut bytes = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+    bytes.extend_from_slice(kind);
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+fn mp4_track(handler: &[u8; 4], format: &[u8; 4], size: Option<(u16, u16)>) -> Vec<u8> {
+    let mut hdlr = vec![0u8; 8];
+    hdlr.extend_from_slice(handler);
+    hdlr.extend_from_slice(&[0u8; 12]);
+    let mut entry = vec![0u8; 36];
+    entry[4..8].copy_from_slice(format);
+    if let Some((width, height)) = size {
+        entry[32..34].copy_from_slice(&width.to_be_bytes());
+        entry[34..36].copy_from_slice(&height.to_be_bytes());
+    }
+    let mut stsd = vec![0, 0, 0, 0, 0, 0, 0, 1];
+    stsd.extend(entry);
+    let stbl = mp4_box(b"stbl", &mp4_box(b"stsd", &stsd));
+    let mut mdia = mp4_box(b"hdlr", &hdlr);
+    mdia.extend(mp4_box(b"minf", &stbl));
+    mp4_box(b"trak", &mp4_box(b"mdia", &mdia))
+}
+
+fn mvhd(seconds: u32) -> Vec<u8> {
+    let mut mvhd = vec![0u8; 12];
+    mvhd.extend_from_slice(&1000u32.to_be_bytes());
+    mvhd.extend_from_slice(&(seconds * 1000).to_be_bytes());
+    mvhd.extend_from_slice(&[0u8; 80]);
+    mp4_box(b"mvhd", &mvhd)
+}
+
+fn with_duration(minutes: u64) -> LocalFile {
+    LocalFile {
+        path: PathBuf::from(format!("{}.mkv", minutes)),
+        media: Some(MediaInfo {
+            duration: Some(Duration::from_secs(minutes * 60)),
+            ..MediaInfo::default()
+        }),
+    }
+}
+
+#[test]
+fn reads_matroska_headers() {
+    let mut segment = info(2652.0);
+    segment.extend(tracks());
+    segment.extend(element(0x1F43_B675, &[0u8; 16]));
+    let mut file = ebml_header();
+    file.extend(element(0x1853_8067, &segment));
+
+    let media = probe(&mut Cursor::new(file)).unwrap();
+    assert_eq!(media.duration, Some(Duration::from_secs(2652)));
+    assert_eq!((media.width, media.height), (Some(1920), Some(1080)));
+    assert_eq!(media.video_codec.as_deref(), Some("HEVC"));
+    assert_eq!(media.audio_codecs, vec!["AAC".to_string()]);
+    assert_eq!(media.summary(), "44:12, 1920x1080, HEVC, AAC");
+}
+
+#[test]
+fn follows_the_seek_head_past_the_first_cluster() {
+    let cluster = element(0x1F43_B675, &[0u8; 64]);
+    let seek_head = |position: u64| {
+        let mut seek = element(0x53AB, &0x1654_AE6Bu32.to_be_bytes());
+        seek.extend(uint(0x53AC, position));
+        element(0x114D_9B74, &element(0x4DBB, &seek))
+    };
+    // The seek head is followed by the info, a cluster and then the tracks.
+    let tracks_position = (seek_head(0).len() + info(5400.0).len() + cluster.len()) as u64;
+    let mut segment = seek_head(tracks_position);
+    segment.extend(info(5400.0));
+    segment.extend(cluster);
+    segment.extend(tracks());
+    let mut file = ebml_header();
+    file.extend(element(0x1853_8067, &segment));
+
+    let media = probe(&mut Cursor::new(file)).unwrap();
+    assert_eq!(media.summary(), "1:30:00, 1920x1080, HEVC, AAC");
+}
+
+#[test]
+fn reads_mp4_headers_after_the_media_data() {
+    let mut moov = mvhd(1320);
+    moov.extend(mp4_track(b"vide", b"avc1", Some((1280, 720))));
+    moov.extend(mp4_track(b"soun", b"ac-3", None));
+    let mut file = mp4_box(b"ftyp", b"isom\0\0\x02\0isomavc1");
+    file.extend(mp4_box(b"mdat", &[0u8; 128]));
+    file.extend(mp4_box(b"moov", &moov));
+
+    let media = probe(&mut Cursor::new(file)).unwrap();
+    assert_eq!(media.summary(), "22:00, 1280x720, H.264, AC-3");
+}
+
+#[test]
+fn rejects_other_files() {
+    assert!(probe(&mut Cursor::new(b"short".to_vec())).is_err());
+    assert!(probe(&mut Cursor::new(b"just some text, not a video".to_vec())).is_err());
+    let mut webm_like = element(0x1A45_DFA3, &element(0x4282, b"other"));
+    webm_like.extend(element(0x1853_8067, &[]));
+    assert!(probe(&mut Cursor::new(webm_like)).is_err());
+}
+
+#[test]
+fn flags_unusual_lengths() {
+    let expected = Duration::from_secs(45 * 60);
+    let check = |minutes: u64| check_duration(Duration::from_secs(minutes * 60), expected);
+    assert_eq!(check(42), DurationCheck::Plausible);
+    assert_eq!(check(20), DurationCheck::TooShort);
+    assert_eq!(check(88), DurationCheck::DoubleEpisode);
+    assert_eq!(check(150), DurationCheck::TooLong);
+
+    assert_eq!(
+        duration_warning(Duration::from_secs(44 * 60), expected),
+        None
+    );
+    assert_eq!(
+        duration_warning(Duration::from_secs(90 * 60), expected).unwrap(),
+        "90 min, twice the usual 45 min: this may be a double episode."
+    );
+}
+
+#[test]
+fn expected_length_prefers_the_provider_runtime() {
+    let files = vec![
+        with_duration(22),
+        with_duration(45),
+        with_duration(23),
+        with_duration(21),
+    ];
+    assert_eq!(
+        expected_duration(Some(47), &files),
+        Some(Duration::from_secs(47 * 60))
+    );
+    assert_eq!(
+        expected_duration(None, &files),
+        Some(Duration::from_secs(23 * 60))
+    );
+    assert_eq!(expected_duration(None, &files[..2]), None);
+
+    let series = |runtime: &str| Series {
+        runtime: runtime.to_string(),
+        ..Series::default()
+    };
+    assert_eq!(series("47 min").runtime_minutes(), Some(47));
+    assert_eq!(series("N/A").runtime_minutes(), None);
+}
+
+#[test]
+fn reads_and_writes_matroska_titles() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("episode.mkv");
+    let mut segment = info_with_title(2652.0, Some("Some.Show.S01E01.720p"));
+    segment.extend(element(0xEC, &[0u8; 100]));
+    segment.extend(tracks());
+    segment.extend(element(0x1F43_B675, &[7u8; 32]));
+    let mut file = ebml_header();
+    file.extend(element(0x1853_8067, &segment));
+    std::fs::write(&path, &file).unwrap();
+    assert_eq!(
+        probe_file(&path).unwrap().title.as_deref(),
+        Some("Some.Show.S01E01.720p")
+    );
+
+    // Longer titles use the padding after the header, the rest of the file stays as it is.
+    let title = "Pilot: A Much Longer Title Than The Release Name Was";
+    write_title(&path, title).unwrap();
+    let media = probe_file(&path).unwrap();
+    assert_eq!(media.title.as_deref(), Some(title));
+    assert_eq!(media.summary(), "44:12, 1920x1080, HEVC, AAC");
+    let written = std::fs::read(&path).unwrap();
+    assert_eq!(written.len(), file.len());
+    assert!(written.ends_with(&element(0x1F43_B675, &[7u8; 32])));
+
+    assert!(write_title(&path, &"x".repeat(300))
+        .unwrap_err()
+        .contains("no room"));
+    assert_eq!(std::fs::read(&path).unwrap(), written);
+}
+
+#[test]
+fn reads_and_writes_mp4_titles() {
+    let dir = tempfile::tempdir().unwrap();
+    let ftyp = mp4_box(b"ftyp", b"isom\0\0\x02\0isomavc1");
+    let mdat = mp4_box(b"mdat", &[9u8; 64]);
+    let mut moov = mvhd(1320);
+    moov.extend(mp4_track(b"vide", b"avc1", Some((1280, 720))));
+
+    // The movie box at the end can grow.
+    let at_end = dir.path().join("at_end.mp4");
+    let mut file = ftyp.clone();
+    file.extend(&mdat);
+    file.extend(mp4_box(b"moov", &moov));
+    std::fs::write(&at_end, &file).unwrap();
+    assert_eq!(probe_file(&at_end).unwrap().title, None);
+    write_title(&at_end, "Pilot").unwrap();
+    let media = probe_file(&at_end).unwrap();
+    assert_eq!(media.title.as_deref(), Some("Pilot"));
+    assert_eq!(media.summary(), "22:00, 1280x720, H.264");
+    assert!(std::fs::read(&at_end)
+        .unwrap()
+        .starts_with(&file[..ftyp.len() + mdat.len()]));
+
+    // In front of the media data, it may only use the free box after it.
+    let in_front = dir.path().join("in_front.mp4");
+    let mut file = ftyp.clone();
+    file.extend(mp4_box(b"moov", &moov));
+    file.extend(mp4_box(b"free", &[0u8; 120]));
+    file.extend(&mdat);
+    std::fs::write(&in_front, &file).unwrap();
+    write_title(&in_front, "Cat's in the Bag").unwrap();
+    assert_eq!(
+        probe_file(&in_front).unwrap().title.as_deref(),
+        Some("Cat's in the Bag")
+    );
+    let written = std::fs::read(&in_front).unwrap();
+    assert_eq!(written.len(), file.len());
+    assert!(written.ends_with(&mdat));
+
+    assert!(write_title(&in_front, &"x".repeat(200))
+        .unwrap_err()
+        .contains("no room"));
+    assert_eq!(std::fs::read(&in_front).unwrap(), written);
+}
+
+/// The file is patched, not replaced, so other names of it see the new
+/// title and it keeps its modification time.
+#[cfg(unix)]
+#[test]
+fn writes_titles_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("in_front.mp4");
+    let mut file = mp4_box(b"ftyp", b"isom\0\0\x02\0isomavc1");
+    file.extend(mp4_box(b"moov", &mvhd(1320)));
+    file.extend(mp4_box(b"free", &[0u8; 120]));
+    file.extend(mp4_box(b"mdat", &[9u8; 64]));
+    std::fs::write(&path, &file).unwrap();
+    let link = dir.path().join("seeding.mp4");
+    std::fs::hard_link(&path, &link).unwrap();
+    let modified = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    write_title(&path, "Pilot").unwrap();
+    assert_eq!(probe_file(&link).unwrap().title.as_deref(), Some("Pilot"));
+    let metadata = std::fs::metadata(&path).unwrap();
+    assert_eq!(metadata.modified().unwrap(), modified);
+    assert_eq!(metadata.len(), file.len() as u64);
+}
+
+#[test]
+fn leaves_headers_it_cannot_read_completely_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // A child with a broken size comes before the title.
+    let mkv = dir.path().join("broken.mkv");
+    let mut payload = uint(0x2A_D7B1, 1_000_000);
+    payload.extend([0x42, 0x87, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+    payload.extend(element(0x7BA9, b"Original"));
+    let mut segment = element(0x1549_A966, &payload);
+    segment.extend(element(0xEC, &[0u8; 100]));
+    segment.extend(tracks());
+    let mut file = ebml_header();
+    file.extend(element(0x1853_8067, &segment));
+    std::fs::write(&mkv, &file).unwrap();
+    assert!(write_title(&mkv, "Pilot")
+        .unwrap_err()
+        .contains("could not be read completely"));
+    assert_eq!(std::fs::read(&mkv).unwrap(), file);
+
+    let mp4 = dir.path().join("broken.mp4");
+    let mut moov = mvhd(1320);
+    moov.extend([0, 0, 0x10, 0, b'u', b'u', b'i', b'd']);
+    moov.extend(mp4_box(b"udta", &[0u8; 16]));
+    let mut file = mp4_box(b"ftyp", b"isom\0\0\x02\0isomavc1");
+    file.extend(mp4_box(b"moov", &moov));
+    std::fs::write(&mp4, &file).unwrap();
+    assert!(write_title(&mp4, "Pilot")
+        .unwrap_err()
+        .contains("could not be read completely"));
+    assert_eq!(std::fs::read(&mp4).unwrap(), file);
+
+    // A Void element after the header that claims more than the file holds.
+    let void = dir.path().join("void.mkv");
+    let mut segment = tracks();
+    segment.extend(element(0x1549_A966, &element(0x7BA9, b"Original")));
+    segment.extend([0xEC, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]);
+    let mut file = ebml_header();
+    file.extend(element(0x1853_8067, &segment));
+    std::fs::write(&void, &file).unwrap();
+    assert!(write_title(&void, &"x".repeat(40))
+        .unwrap_err()
+        .contains("no room"));
+    assert_eq!(std::fs::read(&void).unwrap(), file);
+
+    let names: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert_eq!(names.len(), 3);
+}
+
+#[test]
+fn survives_out_of_range_mp4_sizes() {
+    // A version 1 movie header whose length does not fit into a Duration.
+    let mut mvhd = vec![1u8, 0, 0, 0];
+    mvhd.extend_from_slice(&[0u8; 16]);
+    mvhd.extend_from_slice(&1u32.to_be_bytes());
+    mvhd.extend_from_slice(&(u64::MAX - 1).to_be_bytes());
+    mvhd.extend_from_slice(&[0u8; 80]);
+    let mut file = mp4_box(b"ftyp", b"isom\0\0\x02\0isomavc1");
+    file.extend(mp4_box(b"moov", &mp4_box(b"mvhd", &mvhd)));
+    assert_eq!(probe(&mut Cursor::new(file)).unwrap().duration, None);
+
+    // A box whose 64 bit size runs past the end of any file.
+    let mut file = mp4_box(b"ftyp", b"isom\0\0\x02\0isomavc1");
+    file.extend([0, 0, 0, 1, b'm', b'd', b'a', b't']);
+    file.extend((u64::MAX - 8).to_be_bytes());
+    file.extend(mp4_box(b"moov", &mvhd));
+    assert!(probe(&mut Cursor::new(file.clone()))
+        .unwrap_err()
+        .contains("no movie header"));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("huge.mp4");
+    std::fs::write(&path, &file).unwrap();
+    assert!(write_title(&path, "Pilot").is_err());
+    assert_eq!(std::fs::read(&path).unwrap(), file);
+}
@@ -0,0 +1,39 @@
+//! Tests of the file fallback for the OMDb API key.
+
+use series_renamer::secrets::{FileStore, SecretStore};
+
+#[test]
+fn file_store_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config").join("api_key");
+    let store = FileStore::new(&path);
+    assert_eq!(store.load(), Ok(None));
+
+    store.store("first").unwrap();
+    store.store("second\n").unwrap();
+    assert_eq!(store.load(), Ok(Some("second".to_string())));
+    // Nothing is left behind next to the key.
+    assert_eq!(
+        std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+        1
+    );
+
+    store.delete().unwrap();
+    assert_eq!(store.load(), Ok(None));
+    store.delete().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn file_store_is_only_readable_by_the_user() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("api_key");
+    // An existing file with loose permissions is replaced.
+    std::fs::write(&path, "old").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    FileStore::new(&path).store("secret").unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
@@ -0,0 +1,276 @@
+//! Test helpers: a local stub of the OMDb season and series endpoints and
+//! a temporary series directory with fake episode files.
+
+#![allow(dead_code)] // Not every test file uses every helper.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use series_renamer::omdb::{Client, RetryPolicy};
+
+pub const API_KEY: &str = "testkey";
+
+/// A canned reply of the stub server.
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// Respond with the given status and JSON body.
+    Json(u16, String),
+    /// Accept the connection but never answer.
+    Hang,
+}
+
+/// A minimal HTTP server that answers OMDb style requests.
+///
+/// Seasons are registered with [`StubServer::add_season`], series for title
+/// searches with [`StubServer::add_series`]. Requests with a
+/// wrong `apikey` get OMDb's invalid key error, unknown series or seasons get
+/// its not found error. [`StubServer::push_reply`] queues replies that are
+/// sent before any of that, e.g. to simulate outages.
+pub struct StubServer {
+    pub base_url: String,
+    state: Arc<Mutex<StubState>>,
+    requests: Arc<AtomicUsize>,
+}
+
+#[derive(Default)]
+struct StubState {
+    seasons: HashMap<(String, u32), Vec<(u32, String)>>,
+    /// Series titles by IMDb ID.
+    titles: HashMap<String, String>,
+    queued: Vec<Reply>,
+}
+
+impl StubServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind stub server");
+        let base_url = format!("http://{}/", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(StubState::default()));
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let (thread_state, thread_requests) = (state.clone(), requests.clone());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                thread_requests.fetch_add(1, Ordering::SeqCst);
+                let state = thread_state.clone();
+                std::thread::spawn(move || handle(stream, &state));
+            }
+        });
+
+        Self {
+            base_url,
+            state,
+            requests,
+        }
+    }
+
+    /// Registers the episodes (number, title) of a season.
+    pub fn add_season(&self, imdb_id: &str, season: u32, episodes: &[(u32, &str)]) {
+        let episodes = episodes.iter().map(|(n, t)| (*n, t.to_string())).collect();
+        self.state
+            .lock()
+            .unwrap()
+            .seasons
+            .insert((imdb_id.to_string(), season), episodes);
+    }
+
+    /// Registers a series for title and ID lookups.
+    pub fn add_series(&self, imdb_id: &str, title: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .titles
+            .insert(imdb_id.to_string(), title.to_string());
+    }
+
+    /// Queues a reply that is sent for the next request instead of the
+    /// normal response.
+    pub fn push_reply(&self, reply: Reply) {
+        self.state.lock().unwrap().queued.push(reply);
+    }
+
+    /// Number of requests received so far.
+    pub fn request_count(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+
+    /// A client pointed at this server with short timeouts.
+    pub fn client(&self) -> Client {
+        Client {
+            base_url: self.base_url.clone(),
+            api_key: API_KEY.to_string(),
+            policy: RetryPolicy {
+                timeout: Duration::from_secs(5),
+                max_retries: 2,
+                backoff: Duration::from_millis(10),
+            },
+        }
+    }
+}
+
+fn handle(mut stream: TcpStream, state: &Mutex<StubState>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // Skip the headers, the requests have no body.
+    let mut line = String::new();
+    while reader.read_line(&mut line).map(|n| n > 2).unwrap_or(false) {
+        line.clear();
+    }
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let query: HashMap<String, String> = target
+        .split_once('?')
+        .map(|(_, q)| q)
+        .unwrap_or("")
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    let reply = {
+        let mut state = state.lock().unwrap();
+        if state.queued.is_empty() {
+            respond(&state, &query)
+        } else {
+            state.queued.remove(0)
+        }
+    };
+
+    match reply {
+        Reply::Json(status, body) => {
+            let response = format!(
+                "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+        Reply::Hang => std::thread::sleep(Duration::from_secs(30)),
+    }
+}
+
+fn respond(state: &StubState, query: &HashMap<String, String>) -> Reply {
+    if query.get("apikey").map(String::as_str) != Some(API_KEY) {
+        return Reply::Json(
+            401,
+            r#"{"Response":"False","Error":"Invalid API key!"}"#.to_string(),
+        );
+    }
+    // Title lookups match loosely, like OMDb does.
+    if let Some(title) = query.get("t") {
+        let title = title.replace("%20", " ").to_lowercase();
+        return match state
+            .titles
+            .iter()
+            .find(|(_, t)| t.to_lowercase().contains(&title))
+        {
+            Some((imdb_id, title)) => series_reply(imdb_id, title),
+            None => Reply::Json(
+                200,
+                r#"{"Response":"False","Error":"Series not found!"}"#.to_string(),
+            ),
+        };
+    }
+    let imdb_id = query.get("i").cloned().unwrap_or_default();
+    let Some(season) = query.get("Season").and_then(|s| s.parse::<u32>().ok()) else {
+        let title = state
+            .titles
+            .get(&imdb_id)
+            .map(String::as_str)
+            .unwrap_or("Stub Series");
+        return series_reply(&imdb_id, title);
+    };
+    match state.seasons.get(&(imdb_id, season)) {
+        Some(episodes) => {
+            let episodes: Vec<serde_json::Value> = episodes
+                .iter()
+                .map(|(number, title)| {
+                    serde_json::json!({
+                        "Title": title,
+                        "Released": "2008-01-20",
+                        "Episode": number.to_string(),
+                        "imdbRating": "9.0",
+                        "imdbID": format!("tt9{:06}", number),
+                    })
+                })
+                .collect();
+            let body = serde_json::json!({
+                "Title": "Stub Series",
+                "Season": season.to_string(),
+                "totalSeasons": "5",
+                "Episodes": episodes,
+                "Response": "True",
+            });
+            Reply::Json(200, body.to_string())
+        }
+        None => Reply::Json(
+            200,
+            r#"{"Response":"False","Error":"Series or season not found!"}"#.to_string(),
+        ),
+    }
+}
+
+fn series_reply(imdb_id: &str, title: &str) -> Reply {
+    Reply::Json(
+        200,
+        format!(
+            r#"{{"Title":"{}","imdbID":"{}","Type":"series","Response":"True"}}"#,
+            title, imdb_id
+        ),
+    )
+}
+
+/// A temporary series directory that is deleted when dropped.
+pub struct SeriesDir {
+    dir: tempfile::TempDir,
+}
+
+impl SeriesDir {
+    /// Creates one file per name. Each file contains its own name, so tests
+    /// can check where the content ended up.
+    pub fn with_files(names: &[&str]) -> Self {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        for name in names {
+            let path = dir.path().join(name);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).unwrap();
+            }
+            std::fs::write(&path, name).unwrap();
+        }
+        Self { dir }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    /// Names of all files in the directory, sorted.
+    pub fn file_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(self.path())
+            .unwrap()
+            .filter_map(Result::ok)
+            .filter(|e| e.path().is_file())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// The content of a file, i.e. the name it was created with.
+    pub fn content(&self, name: &str) -> String {
+        std::fs::read_to_string(self.join(name)).unwrap()
+    }
+}
@@ -0,0 +1,217 @@
+//! Tests of the file name template and the checks run before a template or
+//! series profile is saved.
+
+use std::path::PathBuf;
+
+use series_renamer::parse::{parse_release_info, ReleaseInfo};
+use series_renamer::plan::target_file_name;
+use series_renamer::profile::SeriesProfile;
+use series_renamer::sanitize::{NamingOptions, SanitizePolicy};
+use series_renamer::template::{render, validate, TemplateValues, DEFAULT_TEMPLATE};
+use series_renamer::{Episode, LocalFile};
+
+const VALUES: TemplateValues<'static> = TemplateValues {
+    season: 1,
+    episode: "2",
+    title: "Cat's in the Bag",
+    release: None,
+};
+
+#[test]
+fn renders_placeholders_with_padding() {
+    assert_eq!(
+        render(DEFAULT_TEMPLATE, &VALUES),
+        "S01E02 - Cat's in the Bag"
+    );
+    assert_eq!(
+        render("{season}x{episode:03} {title}", &VALUES),
+        "1x002 Cat's in the Bag"
+    );
+    // Only numbers are padded.
+    let special = TemplateValues {
+        episode: "Special",
+        ..VALUES
+    };
+    assert_eq!(
+        render(DEFAULT_TEMPLATE, &special),
+        "S01ESpecial - Cat's in the Bag"
+    );
+}
+
+#[test]
+fn reads_release_details_from_file_names() {
+    let info = parse_release_info("Show.S01E01.1080p.WEB-DL.DDP5.1.Atmos.x264-GRP.mkv");
+    assert_eq!(
+        info,
+        ReleaseInfo {
+            resolution: "1080p".to_string(),
+            source: "WEB-DL".to_string(),
+            video_codec: "x264".to_string(),
+            hdr: String::new(),
+            audio: "DDP5.1 Atmos".to_string(),
+            group: "GRP".to_string(),
+            revision: String::new(),
+        }
+    );
+    assert_eq!(info.quality(), "1080p WEB-DL");
+
+    let info = parse_release_info(
+        "The.Web.S02E03.REPACK.2160p.BluRay.HDR10.DV.H.265.TrueHD.7.1-NTb[rarbg].mkv",
+    );
+    assert_eq!(info.quality(), "2160p BluRay");
+    assert_eq!(info.video_codec, "H.265");
+    assert_eq!(info.hdr, "HDR10 DV");
+    assert_eq!(info.audio, "TrueHD 7.1");
+    assert_eq!(info.group, "NTb");
+    assert_eq!(info.revision, "REPACK");
+
+    assert_eq!(
+        parse_release_info("[SubGroup] Show - 01 [720p].mkv").group,
+        "SubGroup"
+    );
+    assert_eq!(
+        parse_release_info("[SubGroup] Show - 01 [720p].mkv").resolution,
+        "720p"
+    );
+    // Words of the title or a trailing `WEB-DL` are not a group.
+    assert_eq!(
+        parse_release_info("Show - S01E01 - Pilot.mkv"),
+        ReleaseInfo::default()
+    );
+    assert_eq!(parse_release_info("Show.S01E01.WEB-DL.mkv").group, "");
+    // Nor is the second episode of a multi-episode file.
+    assert_eq!(
+        parse_release_info("Show.S01E01-E02.mkv"),
+        ReleaseInfo::default()
+    );
+    assert_eq!(parse_release_info("Show.S01E01-S01E02.mkv").group, "");
+    assert_eq!(
+        parse_release_info("Show.S01E01-E02.720p-GRP.mkv").group,
+        "GRP"
+    );
+}
+
+#[test]
+fn renders_release_details_and_drops_missing_ones() {
+    let release = parse_release_info("Show.S01E02.1080p.WEB-DL.x264-GRP.mkv");
+    let values = TemplateValues {
+        release: Some(&release),
+        ..VALUES
+    };
+    let template = "S{season:02}E{episode:02} - {title} [{quality}]-{group}";
+    assert_eq!(
+        render(template, &values),
+        "S01E02 - Cat's in the Bag [1080p WEB-DL]-GRP"
+    );
+    assert_eq!(
+        render("{title}.{resolution}.{codec}", &values),
+        "Cat's in the Bag.1080p.x264"
+    );
+
+    // Without release details the brackets and separators go as well.
+    assert_eq!(render(template, &VALUES), "S01E02 - Cat's in the Bag");
+    assert_eq!(
+        render("{group} - {episode} {hdr} {title}", &VALUES),
+        "2 Cat's in the Bag"
+    );
+    assert_eq!(
+        render("[{group}] {title} ({audio})", &VALUES),
+        "Cat's in the Bag"
+    );
+    assert!(validate(template).is_ok());
+}
+
+#[test]
+fn rejects_unusable_templates() {
+    assert!(validate(DEFAULT_TEMPLATE).is_ok());
+    assert!(validate("{episode}").is_ok());
+
+    let error = |template: &str| validate(template).unwrap_err();
+    assert!(error("").contains("must not be empty"));
+    assert!(error("Season {season}/{episode}").contains("'/'"));
+    assert!(error("S{season:02} - {title}").contains("{episode}"));
+    assert!(error("{episode} {name}").contains("Unknown placeholder '{name}'"));
+    assert!(error("{episode:xx}").contains("invalid width"));
+    assert!(error("{episode").contains("without a matching '}'"));
+    assert!(error("episode}").contains("without a matching '{'"));
+}
+
+#[test]
+fn template_text_follows_the_filename_policy() {
+    let episode = Episode {
+        title: "Pilot".to_string(),
+        episode: "1".to_string(),
+        imdb_id: String::new(),
+    };
+    let file = LocalFile {
+        path: PathBuf::from("/series/episode.mkv"),
+        media: None,
+    };
+    let name = |policy| {
+        let naming = NamingOptions {
+            policy,
+            template: "{title}: {episode}? <\"{season}\">*|".to_string(),
+            ..NamingOptions::default()
+        };
+        target_file_name(1, &naming, &episode, &file).unwrap()
+    };
+
+    assert_eq!(name(SanitizePolicy::WindowsSafe), "Pilot - 1 '1'-.mkv");
+    assert_eq!(name(SanitizePolicy::SmbSafe), "Pilot - 1 '1'-.mkv");
+    assert_eq!(
+        name(SanitizePolicy::PosixMinimal),
+        "Pilot: 1? <\"1\">*|.mkv"
+    );
+}
+
+#[test]
+fn reserved_device_names_get_a_suffix() {
+    let finalize = |stem: &str| {
+        SanitizePolicy::WindowsSafe
+            .finalize_file_name(stem, "mkv", None)
+            .unwrap()
+    };
+    for reserved in [
+        "CON", "nul", "COM0", "COM1", "com9", "LPT0", "LPT9", "COM¹", "COM³", "LPT²", "CONIN$",
+        "conout$",
+    ] {
+        assert_eq!(finalize(reserved), format!("{}_.mkv", reserved));
+        assert_eq!(
+            finalize(&format!("{}.part1", reserved)),
+            format!("{}.part1_.mkv", reserved)
+        );
+    }
+    for allowed in ["CONSOLE", "COM10", "LPT", "COM⁴", "Connie"] {
+        assert_eq!(finalize(allowed), format!("{}.mkv", allowed));
+    }
+    assert_eq!(
+        SanitizePolicy::PosixMinimal
+            .finalize_file_name("CON", "mkv", None)
+            .unwrap(),
+        "CON.mkv"
+    );
+}
+
+#[test]
+fn profiles_need_a_unique_name_and_a_valid_link() {
+    let profile = SeriesProfile {
+        name: "Breaking Bad".to_string(),
+        imdb_link: "https://www.imdb.com/title/tt0903747/".to_string(),
+        ..Default::default()
+    };
+    assert!(profile.validate(&[]).is_empty());
+
+    let duplicate = profile.validate(std::slice::from_ref(&profile));
+    assert_eq!(
+        duplicate,
+        ["A profile named 'Breaking Bad' already exists."]
+    );
+
+    let broken = SeriesProfile {
+        name: " ".to_string(),
+        imdb_link: "breaking bad".to_string(),
+        base_url: "ftp://example.com".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(broken.validate(&[]).len(), 3);
+}
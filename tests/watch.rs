@@ -0,0 +1,393 @@
+//! Tests of the watch-folder daemon: file name parsing, the rename or review
+//! decision against a local stub of the OMDb API, and a live watcher run.
+
+mod support;
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use series_renamer::omdb::CancelToken;
+use series_renamer::parse::{normalize_title, parse_episode_name, EpisodePattern};
+use series_renamer::rename::RenameOperation;
+use series_renamer::sanitize::NamingOptions;
+use series_renamer::watch::{self, Decision, Identifier, ReviewItem, ReviewQueue, WatchFolder};
+use support::{Reply, SeriesDir, StubServer};
+
+fn stub_with_series() -> StubServer {
+    let server = StubServer::start();
+    server.add_series("tt0903747", "Breaking Bad");
+    server.add_season(
+        "tt0903747",
+        1,
+        &[
+            (1, "Pilot"),
+            (2, "Cat's in the Bag..."),
+            (3, "...And the Bag's in the River"),
+        ],
+    );
+    server
+}
+
+fn folder(dir: &Path) -> WatchFolder {
+    WatchFolder {
+        directory: dir.to_string_lossy().to_string(),
+        ..Default::default()
+    }
+}
+
+fn decide(server: &StubServer, folder: &WatchFolder, path: &Path) -> Decision {
+    Identifier::new(server.client(), NamingOptions::default()).decide(
+        folder,
+        path,
+        &CancelToken::default(),
+    )
+}
+
+fn review_reason(decision: Decision) -> String {
+    match decision {
+        Decision::Review(item) => item.reason,
+        other => panic!("expected a review, got {:?}", other),
+    }
+}
+
+#[test]
+fn parses_common_episode_patterns() {
+    let parsed = parse_episode_name("Breaking.Bad.S01E02.720p.HDTV.x264.mkv").unwrap();
+    assert_eq!(parsed.series, "Breaking Bad");
+    assert_eq!((parsed.season, parsed.episodes.as_slice()), (1, &[2][..]));
+    assert_eq!(parsed.pattern, EpisodePattern::SeasonEpisode);
+
+    let parsed = parse_episode_name("Breaking Bad - 1x03 - Title.avi").unwrap();
+    assert_eq!(parsed.series, "Breaking Bad");
+    assert_eq!((parsed.season, parsed.episodes.as_slice()), (1, &[3][..]));
+    assert_eq!(parsed.pattern, EpisodePattern::Cross);
+
+    let parsed = parse_episode_name("Breaking Bad Season 2 Episode 5.mp4").unwrap();
+    assert_eq!(parsed.series, "Breaking Bad");
+    assert_eq!((parsed.season, parsed.episodes.as_slice()), (2, &[5][..]));
+    assert_eq!(parsed.pattern, EpisodePattern::Words);
+
+    assert_eq!(
+        parse_episode_name("show.s01e01e02.mkv").unwrap().episodes,
+        [1, 2]
+    );
+    assert_eq!(
+        parse_episode_name("show.S01E01-E03.mkv").unwrap().episodes,
+        [1, 2, 3]
+    );
+    assert_eq!(parse_episode_name("S03E04.mkv").unwrap().series, "");
+
+    // Resolutions and plain numbers are not episode numbers.
+    assert_eq!(parse_episode_name("Holiday.1920x1080.mkv"), None);
+    assert_eq!(parse_episode_name("track01.mkv"), None);
+}
+
+#[test]
+fn normalizes_titles_for_comparison() {
+    assert_eq!(normalize_title("Show.Name.2019"), "show name");
+    assert_eq!(normalize_title("Show Name (2019)"), "show name");
+    assert_eq!(normalize_title("Pokémon"), "pokemon");
+    assert_eq!(normalize_title("1917"), "1917");
+}
+
+#[test]
+fn confident_match_is_renamed_in_place() {
+    let server = stub_with_series();
+    let dir = SeriesDir::with_files(&["Breaking.Bad.S01E02.720p.mkv"]);
+    let path = dir.join("Breaking.Bad.S01E02.720p.mkv");
+
+    assert_eq!(
+        decide(&server, &folder(dir.path()), &path),
+        Decision::Rename(RenameOperation {
+            from: path,
+            to: dir.join("S01E02 - Cat's in the Bag.mkv"),
+        })
+    );
+}
+
+#[test]
+fn target_directory_gets_series_and_season_folders() {
+    let server = stub_with_series();
+    let dir = SeriesDir::with_files(&["downloads/Breaking.Bad.S01E01.mkv"]);
+    let path = dir.join("downloads/Breaking.Bad.S01E01.mkv");
+    let folder = WatchFolder {
+        target_directory: dir.join("library").to_string_lossy().to_string(),
+        ..folder(&dir.join("downloads"))
+    };
+
+    let Decision::Rename(operation) = decide(&server, &folder, &path) else {
+        panic!("expected a rename");
+    };
+    assert_eq!(
+        operation.to,
+        dir.join("library/Breaking Bad/Season 01/S01E01 - Pilot.mkv")
+    );
+
+    watch::move_file(&operation).unwrap();
+    assert!(!path.exists());
+    assert_eq!(
+        std::fs::read_to_string(&operation.to).unwrap(),
+        "downloads/Breaking.Bad.S01E01.mkv"
+    );
+}
+
+#[test]
+fn folder_series_is_used_for_bare_episode_numbers() {
+    let server = stub_with_series();
+    let dir = SeriesDir::with_files(&["S01E03.mkv"]);
+    let folder = WatchFolder {
+        imdb_link: "https://www.imdb.com/title/tt0903747/".to_string(),
+        ..folder(dir.path())
+    };
+
+    let Decision::Rename(operation) = decide(&server, &folder, &dir.join("S01E03.mkv")) else {
+        panic!("expected a rename");
+    };
+    assert_eq!(
+        operation.to,
+        dir.join("S01E03 - ...And the Bag's in the River.mkv")
+    );
+}
+
+#[test]
+fn uncertain_matches_go_to_review() {
+    let server = stub_with_series();
+    let dir = SeriesDir::with_files(&[
+        "Breaking.S01E01.mkv",
+        "Breaking.Bad.1x02.mkv",
+        "Breaking.Bad.S01E09.mkv",
+    ]);
+    let folder = folder(dir.path());
+
+    // The title search found a series with a different name.
+    let Decision::Review(item) = decide(&server, &folder, &dir.join("Breaking.S01E01.mkv")) else {
+        panic!("expected a review");
+    };
+    assert!(item
+        .reason
+        .contains("'Breaking' was matched to the series 'Breaking Bad'"));
+    assert_eq!(item.suggestion, Some(dir.join("S01E01 - Pilot.mkv")));
+    assert_eq!(
+        (item.imdb_id.as_deref(), item.season),
+        (Some("tt0903747"), Some(1))
+    );
+
+    let reason = review_reason(decide(&server, &folder, &dir.join("Breaking.Bad.1x02.mkv")));
+    assert!(reason.contains("not written as SxxEyy"), "{}", reason);
+
+    let reason = review_reason(decide(
+        &server,
+        &folder,
+        &dir.join("Breaking.Bad.S01E09.mkv"),
+    ));
+    assert!(reason.contains("Episode 9 does not exist"), "{}", reason);
+}
+
+#[test]
+fn unknown_series_and_names_without_numbers_go_to_review() {
+    let server = stub_with_series();
+    let dir = SeriesDir::with_files(&["Unknown.Show.S01E01.mkv", "holiday.mkv"]);
+    let folder = folder(dir.path());
+
+    let reason = review_reason(decide(
+        &server,
+        &folder,
+        &dir.join("Unknown.Show.S01E01.mkv"),
+    ));
+    assert!(reason.contains("not found"), "{}", reason);
+
+    let reason = review_reason(decide(&server, &folder, &dir.join("holiday.mkv")));
+    assert!(
+        reason.contains("No season and episode number"),
+        "{}",
+        reason
+    );
+}
+
+#[test]
+fn partial_downloads_and_other_files_are_ignored() {
+    let server = stub_with_series();
+    let dir = SeriesDir::with_files(&[
+        "Breaking.Bad.S01E01.mkv.part",
+        "Breaking.Bad.S01E01.nfo",
+        "S01E01 - Pilot.mkv",
+    ]);
+    let folder = WatchFolder {
+        imdb_link: "tt0903747".to_string(),
+        ..folder(dir.path())
+    };
+
+    for name in ["Breaking.Bad.S01E01.mkv.part", "Breaking.Bad.S01E01.nfo"] {
+        assert_eq!(
+            decide(&server, &folder, &dir.join(name)),
+            Decision::Ignore,
+            "{}",
+            name
+        );
+    }
+    // Already named correctly.
+    assert_eq!(
+        decide(&server, &folder, &dir.join("S01E01 - Pilot.mkv")),
+        Decision::Ignore
+    );
+    assert_eq!(server.request_count(), 2);
+}
+
+#[test]
+fn outages_are_retried_later() {
+    let server = stub_with_series();
+    for _ in 0..3 {
+        server.push_reply(Reply::Json(503, "Service Unavailable".to_string()));
+    }
+    let dir = SeriesDir::with_files(&["Breaking.Bad.S01E01.mkv"]);
+
+    let decision = decide(
+        &server,
+        &folder(dir.path()),
+        &dir.join("Breaking.Bad.S01E01.mkv"),
+    );
+    assert!(matches!(decision, Decision::Retry(_)), "{:?}", decision);
+}
+
+#[test]
+fn review_queue_is_kept_on_disk() {
+    let dir = SeriesDir::with_files(&[]);
+    let queue_path = dir.join("review_queue.json");
+    let item = |name: &str, reason: &str| ReviewItem {
+        path: PathBuf::from(name),
+        reason: reason.to_string(),
+        suggestion: None,
+        imdb_id: None,
+        season: None,
+    };
+
+    assert_eq!(
+        ReviewQueue::load_from(&queue_path).unwrap(),
+        ReviewQueue::default()
+    );
+    ReviewQueue::update(&queue_path, |queue| queue.push(item("a.mkv", "first"))).unwrap();
+    ReviewQueue::update(&queue_path, |queue| queue.push(item("b.mkv", "second"))).unwrap();
+    ReviewQueue::update(&queue_path, |queue| queue.push(item("a.mkv", "again"))).unwrap();
+
+    let queue = ReviewQueue::load_from(&queue_path).unwrap();
+    assert_eq!(
+        queue.items,
+        [item("b.mkv", "second"), item("a.mkv", "again")]
+    );
+
+    let queue = ReviewQueue::update(&queue_path, |queue| queue.remove(Path::new("b.mkv"))).unwrap();
+    assert!(!queue.contains(Path::new("b.mkv")));
+    assert_eq!(dir.file_names(), ["review_queue.json"]);
+}
+
+#[test]
+fn concurrent_queue_updates_are_not_lost() {
+    let dir = SeriesDir::with_files(&[]);
+    let queue_path = dir.join("review_queue.json");
+    let writers: Vec<_> = (0..4)
+        .map(|writer| {
+            let queue_path = queue_path.clone();
+            std::thread::spawn(move || {
+                for n in 0..25 {
+                    let item = ReviewItem {
+                        path: PathBuf::from(format!("{}-{}.mkv", writer, n)),
+                        reason: String::new(),
+                        suggestion: None,
+                        imdb_id: None,
+                        season: None,
+                    };
+                    ReviewQueue::update(&queue_path, |queue| queue.push(item)).unwrap();
+                }
+            })
+        })
+        .collect();
+    writers
+        .into_iter()
+        .for_each(|writer| writer.join().unwrap());
+
+    assert_eq!(
+        ReviewQueue::load_from(&queue_path).unwrap().items.len(),
+        100
+    );
+    assert_eq!(dir.file_names(), ["review_queue.json"]);
+}
+
+#[test]
+fn move_file_never_overwrites() {
+    let dir = SeriesDir::with_files(&["a.mkv", "b.mkv"]);
+    let operation = RenameOperation {
+        from: dir.join("a.mkv"),
+        to: dir.join("b.mkv"),
+    };
+
+    assert!(watch::move_file(&operation).is_err());
+    assert_eq!(dir.content("a.mkv"), "a.mkv");
+    assert_eq!(dir.content("b.mkv"), "b.mkv");
+}
+
+/// Polls `condition` for up to ten seconds.
+fn wait_for(condition: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+#[test]
+fn watcher_renames_new_episodes_and_queues_the_rest() {
+    let server = stub_with_series();
+    let dir = SeriesDir::with_files(&[]);
+    let queue_path = dir.join("queue/review_queue.json");
+    let watched = dir.join("incoming");
+    std::fs::create_dir(&watched).unwrap();
+    // Downloaded while the daemon was not running.
+    std::fs::write(watched.join("Breaking.Bad.S01E03.mkv"), "episode").unwrap();
+
+    let cancel = CancelToken::default();
+    let daemon = {
+        let (folders, identifier) = (
+            vec![folder(&watched)],
+            Identifier::new(server.client(), NamingOptions::default()),
+        );
+        let (queue_path, cancel) = (queue_path.clone(), cancel.clone());
+        std::thread::spawn(move || {
+            watch::watch(
+                &folders,
+                Duration::from_secs(1),
+                identifier,
+                &queue_path,
+                &cancel,
+            )
+        })
+    };
+    // Give the watcher time to register before creating files.
+    std::thread::sleep(Duration::from_millis(500));
+
+    std::fs::write(watched.join("Breaking.Bad.S01E02.mkv"), "episode").unwrap();
+    std::fs::write(watched.join("Breaking.Bad.1x03.mkv"), "episode").unwrap();
+    std::fs::write(watched.join("Breaking.Bad.S01E01.mkv.part"), "episode").unwrap();
+
+    let renamed = watched.join("S01E02 - Cat's in the Bag.mkv");
+    assert!(wait_for(|| renamed.exists()), "the episode was not renamed");
+    let existing = watched.join("S01E03 - ...And the Bag's in the River.mkv");
+    assert!(
+        wait_for(|| existing.exists()),
+        "the episode from before the start was not renamed"
+    );
+    assert!(
+        wait_for(|| ReviewQueue::load_from(&queue_path)
+            .is_ok_and(|queue| queue.contains(&watched.join("Breaking.Bad.1x03.mkv")))),
+        "the uncertain episode was not queued"
+    );
+
+    cancel.cancel();
+    daemon.join().unwrap().unwrap();
+
+    assert!(watched.join("Breaking.Bad.1x03.mkv").exists());
+    assert!(watched.join("Breaking.Bad.S01E01.mkv.part").exists());
+    assert_eq!(ReviewQueue::load_from(&queue_path).unwrap().items.len(), 1);
+}
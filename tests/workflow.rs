@@ -0,0 +1,582 @@
+//! End-to-end tests of the scan → plan → apply → undo workflow against a
+//! local stub of the OMDb API.
+
+mod support;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use series_renamer::duplicates::{find_duplicates, move_files, plan_moves, resolve_folder};
+use series_renamer::omdb::{CancelToken, OmdbError};
+use series_renamer::plan::{
+    assign_sequentially, build_rename_plan, episode_for_tag, parse_episode_list, plan_operations,
+    target_file_name, EpisodeList,
+};
+use series_renamer::rename::{
+    apply_renames, apply_renames_transactional, undo_renames, RenameOperation, RenameStatus,
+};
+use series_renamer::sanitize::{NamingOptions, SanitizePolicy};
+use series_renamer::scan::{imdb_id_from_link, scan_directory, scan_directory_except};
+use series_renamer::{Episode, LocalFile};
+use support::{Reply, SeriesDir, StubServer};
+
+const IMDB_LINK: &str = "https://www.imdb.com/title/tt0903747/";
+
+fn stub_with_season() -> StubServer {
+    let server = StubServer::start();
+    server.add_season(
+        "tt0903747",
+        1,
+        &[
+            (1, "Pilot"),
+            (2, "Cat's in the Bag..."),
+            (3, "...And the Bag's in the River"),
+        ],
+    );
+    server
+}
+
+/// Maps file names to the episode numbers typed next to them.
+fn inputs(dir: &SeriesDir, assignments: &[(&str, &str)]) -> HashMap<PathBuf, String> {
+    assignments
+        .iter()
+        .map(|(name, number)| (dir.join(name), number.to_string()))
+        .collect()
+}
+
+#[test]
+fn scan_plan_apply_and_undo() {
+    let server = stub_with_season();
+    let dir = SeriesDir::with_files(&["track10.mkv", "track2.mkv", "track1.mkv", "notes.txt"]);
+
+    // Scan
+    let files = scan_directory(dir.path());
+    let names: Vec<String> = files
+        .iter()
+        .map(|f| f.path.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(
+        names,
+        ["notes.txt", "track1.mkv", "track2.mkv", "track10.mkv"]
+    );
+
+    // Fetch
+    let imdb_id = imdb_id_from_link(IMDB_LINK).unwrap();
+    let episodes = server
+        .client()
+        .fetch_season(&imdb_id, 1, &CancelToken::default(), &|_, _| {})
+        .unwrap();
+    assert_eq!(episodes.len(), 3);
+
+    // Plan
+    let inputs = inputs(
+        &dir,
+        &[
+            ("track1.mkv", "1"),
+            ("track2.mkv", "02"),
+            ("track10.mkv", "3"),
+            ("notes.txt", ""),
+        ],
+    );
+    let plan = build_rename_plan(&episodes, &files, &inputs, 1, &NamingOptions::default());
+    assert!(plan.issues.is_empty(), "{:?}", plan.issues);
+    let (operations, failures) = plan_operations(&plan.entries, 1, &NamingOptions::default());
+    assert!(failures.is_empty());
+
+    // Apply
+    let outcomes = apply_renames(&operations);
+    assert!(outcomes.iter().all(|o| o.status == RenameStatus::Renamed));
+    assert_eq!(
+        dir.file_names(),
+        [
+            "S01E01 - Pilot.mkv",
+            "S01E02 - Cat's in the Bag.mkv",
+            "S01E03 - ...And the Bag's in the River.mkv",
+            "notes.txt",
+        ]
+    );
+    assert_eq!(
+        dir.content("S01E03 - ...And the Bag's in the River.mkv"),
+        "track10.mkv"
+    );
+
+    // Undo
+    let undone = undo_renames(&outcomes);
+    assert!(undone.iter().all(|o| o.status == RenameStatus::Renamed));
+    assert_eq!(
+        dir.file_names(),
+        ["notes.txt", "track1.mkv", "track10.mkv", "track2.mkv"]
+    );
+    assert_eq!(dir.content("track10.mkv"), "track10.mkv");
+}
+
+#[test]
+fn unusable_inputs_are_reported() {
+    let server = stub_with_season();
+    let dir = SeriesDir::with_files(&["a.mkv", "b.mkv", "c.mkv", "d.mkv"]);
+    let files = scan_directory(dir.path());
+    let episodes = server
+        .client()
+        .fetch_season("tt0903747", 1, &CancelToken::default(), &|_, _| {})
+        .unwrap();
+
+    let inputs = inputs(
+        &dir,
+        &[
+            ("a.mkv", "1"),
+            ("b.mkv", "01"),
+            ("c.mkv", "9"),
+            ("d.mkv", "x"),
+        ],
+    );
+    let plan = build_rename_plan(&episodes, &files, &inputs, 1, &NamingOptions::default());
+
+    assert_eq!(plan.entries.len(), 1);
+    assert_eq!(plan.issues.len(), 3, "{:?}", plan.issues);
+}
+
+#[test]
+fn swapped_names_are_resolved() {
+    let server = stub_with_season();
+    // The files carry each other's names.
+    let dir = SeriesDir::with_files(&["S01E01 - Pilot.mkv", "S01E02 - Cat's in the Bag.mkv"]);
+    let files = scan_directory(dir.path());
+    let episodes = server
+        .client()
+        .fetch_season("tt0903747", 1, &CancelToken::default(), &|_, _| {})
+        .unwrap();
+
+    let inputs = inputs(
+        &dir,
+        &[
+            ("S01E01 - Pilot.mkv", "2"),
+            ("S01E02 - Cat's in the Bag.mkv", "1"),
+        ],
+    );
+    let plan = build_rename_plan(&episodes, &files, &inputs, 1, &NamingOptions::default());
+    let (operations, _) = plan_operations(&plan.entries, 1, &NamingOptions::default());
+
+    for apply in [apply_renames, apply_renames_transactional] {
+        let outcomes = apply(&operations);
+        assert!(
+            outcomes.iter().all(|o| o.status == RenameStatus::Renamed),
+            "{:?}",
+            outcomes
+        );
+        assert_eq!(
+            dir.content("S01E01 - Pilot.mkv"),
+            "S01E02 - Cat's in the Bag.mkv"
+        );
+        assert_eq!(
+            dir.content("S01E02 - Cat's in the Bag.mkv"),
+            "S01E01 - Pilot.mkv"
+        );
+
+        // Swap back for the next round.
+        undo_renames(&outcomes);
+        assert_eq!(dir.content("S01E01 - Pilot.mkv"), "S01E01 - Pilot.mkv");
+    }
+}
+
+#[test]
+fn leftover_temporary_files_are_not_overwritten() {
+    let server = stub_with_season();
+    let dir = SeriesDir::with_files(&["S01E01 - Pilot.mkv", "S01E02 - Cat's in the Bag.mkv"]);
+    // A file left behind by an earlier run that had the same process ID.
+    let leftover = format!(".series_renamer_{}_0.tmp", std::process::id());
+    std::fs::write(dir.join(&leftover), "leftover").unwrap();
+    let files = scan_directory(dir.path());
+    let episodes = server
+        .client()
+        .fetch_season("tt0903747", 1, &CancelToken::default(), &|_, _| {})
+        .unwrap();
+
+    let inputs = inputs(
+        &dir,
+        &[
+            ("S01E01 - Pilot.mkv", "2"),
+            ("S01E02 - Cat's in the Bag.mkv", "1"),
+        ],
+    );
+    let plan = build_rename_plan(&episodes, &files, &inputs, 1, &NamingOptions::default());
+    let (operations, _) = plan_operations(&plan.entries, 1, &NamingOptions::default());
+
+    for apply in [apply_renames, apply_renames_transactional] {
+        let outcomes = apply(&operations);
+        assert!(
+            outcomes.iter().all(|o| o.status == RenameStatus::Renamed),
+            "{:?}",
+            outcomes
+        );
+        assert_eq!(dir.content(&leftover), "leftover");
+        assert_eq!(
+            dir.content("S01E01 - Pilot.mkv"),
+            "S01E02 - Cat's in the Bag.mkv"
+        );
+        assert_eq!(dir.file_names().len(), 3);
+
+        undo_renames(&outcomes);
+        assert_eq!(dir.content(&leftover), "leftover");
+    }
+}
+
+/// A hard link under the other name stands in for a case-insensitive file
+/// system, where `Pilot.mkv` "exists" because it is `pilot.mkv` itself.
+#[cfg(unix)]
+#[test]
+fn case_only_renames_are_not_conflicts() {
+    for apply in [apply_renames, apply_renames_transactional] {
+        let dir = SeriesDir::with_files(&["pilot.mkv"]);
+        std::fs::hard_link(dir.join("pilot.mkv"), dir.join("Pilot.mkv")).unwrap();
+        let operations = [RenameOperation {
+            from: dir.join("pilot.mkv"),
+            to: dir.join("Pilot.mkv"),
+        }];
+
+        let outcomes = apply(&operations);
+        assert_eq!(outcomes[0].status, RenameStatus::Renamed, "{:?}", outcomes);
+        assert_eq!(dir.content("Pilot.mkv"), "pilot.mkv");
+    }
+}
+
+#[test]
+fn transactional_apply_rolls_back_on_conflict() {
+    let server = stub_with_season();
+    let dir = SeriesDir::with_files(&["a.mkv", "b.mkv", "S01E02 - Cat's in the Bag.mkv"]);
+    let episodes = server
+        .client()
+        .fetch_season("tt0903747", 1, &CancelToken::default(), &|_, _| {})
+        .unwrap();
+    // Only plan the first two files, so the third one blocks a target name.
+    let files: Vec<_> = scan_directory(dir.path())
+        .into_iter()
+        .filter(|f| f.path.file_name().unwrap().len() == 5)
+        .collect();
+
+    let inputs = inputs(&dir, &[("a.mkv", "1"), ("b.mkv", "2")]);
+    let plan = build_rename_plan(&episodes, &files, &inputs, 1, &NamingOptions::default());
+    let (operations, _) = plan_operations(&plan.entries, 1, &NamingOptions::default());
+
+    let outcomes = apply_renames_transactional(&operations);
+    assert!(outcomes.iter().all(|o| o.status == RenameStatus::Failed));
+    assert_eq!(
+        dir.file_names(),
+        ["S01E02 - Cat's in the Bag.mkv", "a.mkv", "b.mkv"]
+    );
+    assert_eq!(
+        dir.content("S01E02 - Cat's in the Bag.mkv"),
+        "S01E02 - Cat's in the Bag.mkv"
+    );
+
+    // Without a transaction, the file that can be renamed is renamed and
+    // the existing file is not overwritten.
+    let outcomes = apply_renames(&operations);
+    assert_eq!(outcomes[0].status, RenameStatus::Renamed);
+    assert_eq!(outcomes[1].status, RenameStatus::Failed);
+    assert_eq!(
+        dir.content("S01E02 - Cat's in the Bag.mkv"),
+        "S01E02 - Cat's in the Bag.mkv"
+    );
+}
+
+#[test]
+fn transactional_apply_rolls_back_a_failed_step() {
+    // The target directory of one rename does not exist, so moving it out of
+    // its temporary name fails after the other files have been renamed.
+    let names = ["a.mkv", "b.mkv", "c.mkv"];
+    for failing in 0..names.len() {
+        let dir = SeriesDir::with_files(&names);
+        let operations: Vec<RenameOperation> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| RenameOperation {
+                from: dir.join(name),
+                to: if index == failing {
+                    dir.join("missing").join(format!("S01E0{}.mkv", index + 1))
+                } else {
+                    dir.join(&format!("S01E0{}.mkv", index + 1))
+                },
+            })
+            .collect();
+
+        let outcomes = apply_renames_transactional(&operations);
+        assert!(
+            outcomes.iter().all(|o| o.status == RenameStatus::Failed),
+            "{:?}",
+            outcomes
+        );
+        assert!(
+            outcomes[failing]
+                .error
+                .as_deref()
+                .unwrap()
+                .contains("(Rolled back)"),
+            "{:?}",
+            outcomes
+        );
+        // No file is left behind under a temporary name.
+        assert_eq!(dir.file_names(), names);
+        for name in names {
+            assert_eq!(dir.content(name), name);
+        }
+    }
+}
+
+#[test]
+fn omdb_errors_are_typed() {
+    let server = stub_with_season();
+
+    let mut client = server.client();
+    client.api_key = "wrong".to_string();
+    let result = client.fetch_season("tt0903747", 1, &CancelToken::default(), &|_, _| {});
+    assert!(
+        matches!(result, Err(OmdbError::InvalidApiKey(_))),
+        "{:?}",
+        result
+    );
+    assert!(matches!(
+        client.test_api_key(),
+        Err(OmdbError::InvalidApiKey(_))
+    ));
+
+    let result = server
+        .client()
+        .fetch_season("tt0903747", 7, &CancelToken::default(), &|_, _| {});
+    assert!(
+        matches!(result, Err(OmdbError::NotFound(_))),
+        "{:?}",
+        result
+    );
+
+    server.push_reply(Reply::Json(
+        200,
+        r#"{"Response":"False","Error":"Request limit reached!"}"#.to_string(),
+    ));
+    let result = server
+        .client()
+        .fetch_season("tt0903747", 1, &CancelToken::default(), &|_, _| {});
+    assert!(
+        matches!(result, Err(OmdbError::RateLimited(_))),
+        "{:?}",
+        result
+    );
+    let message = result.unwrap_err().with_guidance();
+    assert!(
+        message.starts_with("Rate limit reached: Request limit reached!\nThe free key"),
+        "{}",
+        message
+    );
+    assert_eq!(OmdbError::Cancelled.with_guidance(), "Cancelled");
+
+    assert_eq!(server.client().test_api_key(), Ok(()));
+}
+
+#[test]
+fn transient_failures_are_retried() {
+    let server = stub_with_season();
+    server.push_reply(Reply::Json(503, "Service Unavailable".to_string()));
+    server.push_reply(Reply::Json(502, "Bad Gateway".to_string()));
+
+    let retries = AtomicU32::new(0);
+    let episodes = server
+        .client()
+        .fetch_season("tt0903747", 1, &CancelToken::default(), &|_, _| {
+            retries.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+    assert_eq!(episodes.len(), 3);
+    assert_eq!(retries.load(Ordering::SeqCst), 2);
+    assert_eq!(server.request_count(), 3);
+}
+
+#[test]
+fn hanging_requests_time_out_and_can_be_cancelled() {
+    let server = stub_with_season();
+    let mut client = server.client();
+    client.policy.timeout = Duration::from_millis(300);
+    client.policy.max_retries = 0;
+
+    server.push_reply(Reply::Hang);
+    let result = client.fetch_season("tt0903747", 1, &CancelToken::default(), &|_, _| {});
+    assert!(matches!(result, Err(OmdbError::Timeout(_))), "{:?}", result);
+
+    server.push_reply(Reply::Hang);
+    client.policy.timeout = Duration::from_secs(30);
+    let cancel = CancelToken::default();
+    let canceller = cancel.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        canceller.cancel();
+    });
+    let result = client.fetch_season("tt0903747", 1, &cancel, &|_, _| {});
+    assert_eq!(result, Err(OmdbError::Cancelled));
+}
+
+#[test]
+fn sequential_assignment_follows_natural_order() {
+    let dir = SeriesDir::with_files(&["track10.mkv", "track2.mkv", "track1.mkv", "notes.txt"]);
+    let files = scan_directory(dir.path());
+    let episodes: Vec<Episode> = (1..=6)
+        .map(|n| Episode {
+            title: format!("Episode {}", n),
+            episode: n.to_string(),
+            imdb_id: String::new(),
+        })
+        .collect();
+
+    let skip = parse_episode_list("3, 5-5").unwrap();
+    let assigned = assign_sequentially(&episodes, &files, 1, &skip);
+    assert_eq!(
+        assigned,
+        inputs(
+            &dir,
+            &[
+                ("track1.mkv", "2"),
+                ("track2.mkv", "4"),
+                ("track10.mkv", "6")
+            ]
+        )
+    );
+
+    // Files beyond the last episode stay unassigned.
+    let assigned = assign_sequentially(&episodes, &files, 4, &EpisodeList::default());
+    assert_eq!(
+        assigned,
+        inputs(&dir, &[("track1.mkv", "5"), ("track2.mkv", "6")])
+    );
+
+    // Starting after an episode goes by its number, not by a count.
+    let without_third: Vec<Episode> = episodes
+        .iter()
+        .filter(|e| e.episode != "3")
+        .cloned()
+        .collect();
+    let assigned = assign_sequentially(&without_third, &files, 3, &EpisodeList::default());
+    assert_eq!(
+        assigned,
+        inputs(
+            &dir,
+            &[
+                ("track1.mkv", "4"),
+                ("track2.mkv", "5"),
+                ("track10.mkv", "6")
+            ]
+        )
+    );
+
+    let list = parse_episode_list(" 01,7-9 ").unwrap();
+    assert!(["1", "07", "8", "9"].iter().all(|n| list.contains(n)));
+    assert!(!list.contains("2") && !list.contains("10") && !list.contains("x"));
+    let list = parse_episode_list("3 ,7 - 9; 12 -13").unwrap();
+    assert!(["3", "7", "8", "9", "12", "13"]
+        .iter()
+        .all(|n| list.contains(n)));
+    assert!(!list.contains("10"));
+    // Ranges are not expanded, so a huge one costs nothing.
+    assert!(parse_episode_list("1-4000000000")
+        .unwrap()
+        .contains("3999999999"));
+    assert!(parse_episode_list("9-7").is_err());
+    assert!(parse_episode_list("x").unwrap_err().contains("'x'"));
+    assert!(parse_episode_list("3-").is_err());
+}
+
+#[test]
+fn title_tags_point_to_episodes() {
+    let episodes: Vec<Episode> = [
+        (1, "Pilot"),
+        (2, "Cat's in the Bag..."),
+        (3, "Grilled"),
+        (4, "Grilled Again"),
+    ]
+    .iter()
+    .map(|(n, title)| Episode {
+        title: title.to_string(),
+        episode: n.to_string(),
+        imdb_id: String::new(),
+    })
+    .collect();
+    let number = |tag: &str| episode_for_tag(&episodes, 1, tag).map(|e| e.episode.as_str());
+
+    assert_eq!(number("pilot"), Some("1"));
+    assert_eq!(
+        number("Breaking Bad - S01E02 - Cat's in the Bag"),
+        Some("2")
+    );
+    // An exact title wins over titles the tag merely ends with.
+    assert_eq!(number("Grilled"), Some("3"));
+    assert_eq!(number("Breaking.Bad.S01E04.720p.WEB"), Some("4"));
+    assert_eq!(number("Breaking.Bad.S02E04.720p.WEB"), None);
+    assert_eq!(number("Some Movie"), None);
+}
+
+#[test]
+fn long_names_shorten_only_the_title() {
+    let episode = Episode {
+        title: "x".repeat(300),
+        episode: "1".to_string(),
+        imdb_id: String::new(),
+    };
+    let file = |parent: &str| LocalFile {
+        path: PathBuf::from(format!("/{}/a.mkv", parent)),
+        media: None,
+    };
+
+    // Names are limited to 255 characters, the path only on Windows and SMB shares.
+    let naming = NamingOptions::default();
+    let name = target_file_name(1, &naming, &episode, &file(&"d".repeat(240))).unwrap();
+    assert!(
+        name.starts_with("S01E01 - xxx") && name.ends_with("x.mkv"),
+        "{}",
+        name
+    );
+    if !cfg!(windows) {
+        assert_eq!(name.len(), 255);
+    }
+
+    let smb = NamingOptions {
+        policy: SanitizePolicy::SmbSafe,
+        ..NamingOptions::default()
+    };
+    let name = target_file_name(1, &smb, &episode, &file(&"d".repeat(240))).unwrap();
+    assert_eq!(name, "S01E01 - xxxx.mkv");
+    // Rather than cutting into the episode number, the file is reported.
+    let deep = file(&"d".repeat(250));
+    assert_eq!(
+        target_file_name(1, &smb, &episode, &deep),
+        Err("the new path would be too long".to_string())
+    );
+    let inputs = HashMap::from([(deep.path.clone(), "1".to_string())]);
+    let plan = build_rename_plan(&[episode], &[deep], &inputs, 1, &smb);
+    assert!(plan.entries.is_empty());
+    assert_eq!(plan.issues, ["'a.mkv': the new path would be too long."]);
+}
+
+#[test]
+fn moved_copies_are_not_scanned_again() {
+    let dir = SeriesDir::with_files(&[
+        "Show.S01E01.720p.mkv",
+        "Show.S01E01.1080p.mkv",
+        "Show.S01E02.mkv",
+    ]);
+    let folder = resolve_folder(dir.path(), "");
+    let groups = find_duplicates(&scan_directory_except(dir.path(), Some(&folder)));
+    assert_eq!(groups.len(), 1);
+    let outcomes = move_files(&plan_moves(
+        &[groups[0].files[0].file.path.clone()],
+        &folder,
+    ));
+    assert_eq!(outcomes[0].status, RenameStatus::Renamed);
+
+    let files = scan_directory_except(dir.path(), Some(&folder));
+    let names: Vec<_> = files
+        .iter()
+        .map(|f| f.path.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(names, ["Show.S01E01.1080p.mkv", "Show.S01E02.mkv"]);
+    assert!(find_duplicates(&files).is_empty());
+    assert_eq!(scan_directory(dir.path()).len(), 3);
+}